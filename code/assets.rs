@@ -0,0 +1,63 @@
+// generated by forge from the sheets in the assets, do not edit
+#![allow(dead_code, non_camel_case_types)]
+
+pub mod tiles {
+    pub const PATH: &str = "tiles.sheet.ron";
+    pub const FRAME_COUNT: usize = 4;
+
+    #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+    pub enum Tile {
+        Dirt,
+        Grass,
+        Stone,
+        Water,
+    }
+    impl Tile {
+        pub const ALL: [Self; 4] = [Self::Dirt, Self::Grass, Self::Stone, Self::Water];
+
+        pub fn name(self) -> &'static str {
+            match self {
+                Self::Dirt => "dirt",
+                Self::Grass => "grass",
+                Self::Stone => "stone",
+                Self::Water => "water",
+            }
+        }
+
+        pub fn frames(self) -> std::ops::Range<usize> {
+            match self {
+                Self::Dirt => 0..1,
+                Self::Grass => 1..2,
+                Self::Stone => 2..3,
+                Self::Water => 3..4,
+            }
+        }
+    }
+}
+
+pub mod lira {
+    pub mod idle {
+        pub const PATH: &str = "lira/idle.sheet.ron";
+        pub const FRAME_COUNT: usize = 8;
+
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+        pub enum Animation {
+            Idle,
+        }
+        impl Animation {
+            pub const ALL: [Self; 1] = [Self::Idle];
+
+            pub fn name(self) -> &'static str {
+                match self {
+                    Self::Idle => "idle",
+                }
+            }
+
+            pub fn frames(self) -> std::ops::Range<usize> {
+                match self {
+                    Self::Idle => 0..8,
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use crate::*;
 use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
     render::{
         camera::RenderTarget,
         render_resource::{
@@ -9,13 +10,31 @@ use bevy::{
         view::RenderLayers,
     },
     sprite::MaterialMesh2dBundle,
-    window::WindowResized,
+    window::PrimaryWindow,
 };
 
 pub const PIXEL_LAYER: RenderLayers = RenderLayers::layer(0);
 pub const HIGH_RES_LAYERS: RenderLayers = RenderLayers::layer(1);
 
 const PIXEL_SIZE: f32 = 2.5;
+const SCALING: ScalingMode = ScalingMode::Integer;
+pub const VIRTUAL_RESOLUTION: UVec2 = UVec2::new(480, 270);
+const BAR_COLOR: Color = Color::BLACK;
+// multiples of the pixel size, the virtual resolution is divided by them
+const ZOOM_STEPS: [f32; 4] = [0.5, 1.0, 2.0, 4.0];
+const DEFAULT_ZOOM_STEP: usize = 1;
+// rate per second the zoom closes in on its step
+const ZOOM_SPEED: f32 = 12.0;
+// touchpads scroll by pixels, this many make one zoom step like a wheel line
+const WHEEL_STEP_PIXELS: f32 = 100.0;
+// game pixels rendered around the visible canvas for sub-pixel scrolling
+const CANVAS_MARGIN: u32 = 1;
+const FOLLOW: Follow = Follow {
+    smoothing: 6.0,
+    dead_zone: Vec2::new(12.0, 8.0),
+    look_ahead: 0.4,
+    stick_look: 48.0,
+};
 
 const TEST_COLOR: Color = Color::srgb(0.7, 0.3, 0.5);
 
@@ -25,13 +44,93 @@ pub struct CameraResource {
     pub outer_camera: Entity,
     pub canvas: Entity,
     pub canvas_image: Handle<Image>,
-    // pub zoom: f32,
+    // index into ZOOM_STEPS, zoom animates towards it
+    pub zoom_step: usize,
+    pub zoom: f32,
+    // target screen pixels per game pixel, see ScalingMode
     pub pixel_size: f32,
+    pub scaling: ScalingMode,
+    // screen pixels per game pixel as applied by fit_canvas
+    pub canvas_scale: f32,
+    // where the camera looks, the pixel camera renders it snapped to whole
+    // game pixels and the canvas is moved by the remainder
+    pub position: Vec2,
+    // centers the canvas on screen pixels, set by fit_canvas
+    pub canvas_offset: Vec2,
     pub followed_entity: Option<Entity>,
+    pub follow: Follow,
+    // keeps the view from showing anything beyond the tilemap
+    pub clamp_to_map: bool,
+    // visible game pixels, the canvas image has a margin around them
     pub pixel_w: u32,
     pub pixel_h: u32,
 }
 
+// how the canvas is sized and scaled to the window, switched at runtime
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ScalingMode {
+    // whole screen pixels per game pixel, at most pixel_size, with bars
+    Integer,
+    // exactly pixel_size, the canvas covers the window and may crop a pixel
+    Fill,
+    // a canvas of this resolution scaled by a whole factor, with bars
+    Virtual(UVec2),
+}
+impl ScalingMode {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Integer => Self::Fill,
+            Self::Fill => Self::Virtual(VIRTUAL_RESOLUTION),
+            Self::Virtual(_) => Self::Integer,
+        }
+    }
+
+    // canvas resolution and screen pixels per game pixel for a window size
+    pub fn fit(
+        self,
+        window: UVec2,
+        pixel_size: f32,
+        zoom: f32,
+    ) -> (UVec2, f32) {
+        let window = window.as_vec2();
+        let pixel_size = pixel_size * zoom;
+        match self {
+            Self::Integer => {
+                let scale = pixel_size.floor().max(1.0);
+                ((window / scale).floor().as_uvec2().max(UVec2::ONE), scale)
+            }
+            Self::Fill => (
+                (window / pixel_size).ceil().as_uvec2().max(UVec2::ONE),
+                pixel_size,
+            ),
+            Self::Virtual(resolution) => {
+                let resolution = (resolution.as_vec2() / zoom)
+                    .round()
+                    .as_uvec2()
+                    .max(UVec2::ONE);
+                let scale = (window / resolution.as_vec2())
+                    .min_element()
+                    .floor()
+                    .max(1.0);
+                (resolution, scale)
+            }
+        }
+    }
+}
+
+// how the camera trails the followed entity, distances in game pixels
+#[derive(Clone, Copy, Debug)]
+pub struct Follow {
+    // rate per second the camera closes in on the target, higher is snappier
+    pub smoothing: f32,
+    // half size of the box around the view center the target moves freely in
+    pub dead_zone: Vec2,
+    // seconds of the target's velocity the camera looks ahead
+    pub look_ahead: f32,
+    // offset of the view with the right stick fully tilted
+    pub stick_look: f32,
+}
+
 #[derive(Component)]
 struct InGameCamera;
 
@@ -41,35 +140,50 @@ struct OuterCamera;
 #[derive(Component)]
 struct Canvas;
 
+// letterbox or pillarbox bar on this side of the canvas, drawn over its
+// margin so only the visible game pixels show
+#[derive(Component)]
+struct Bar(Vec2);
+
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup_cameras).add_systems(
             Update,
-            (follow.after(motion::update_position), fit_canvas),
+            (
+                follow.after(motion::update_position),
+                zoom,
+                fit_canvas.after(zoom),
+                clamp_to_map.after(follow).after(fit_canvas),
+                snap_camera.after(clamp_to_map),
+            ),
         );
     }
 }
 
+fn canvas_extent(pixel_size: UVec2) -> Extent3d {
+    Extent3d {
+        width: pixel_size.x + 2 * CANVAS_MARGIN,
+        height: pixel_size.y + 2 * CANVAS_MARGIN,
+        ..default()
+    }
+}
+
 pub fn setup_cameras(
     mut commands: Commands,
     mut images: ResMut<Assets<Image>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    windows: Query<&mut Window>,
+    windows: Query<&Window, With<PrimaryWindow>>,
 ) {
-    let window_w = windows.single().resolution.width();
-    let window_h = windows.single().resolution.height();
+    let (pixel_size, canvas_scale) = SCALING.fit(
+        windows.single().physical_size(),
+        PIXEL_SIZE,
+        ZOOM_STEPS[DEFAULT_ZOOM_STEP],
+    );
 
-    let pixel_w = (window_w / PIXEL_SIZE).floor() as u32;
-    let pixel_h = (window_h / PIXEL_SIZE).floor() as u32;
-
-    let canvas_size = Extent3d {
-        width: pixel_w,
-        height: pixel_h,
-        ..default()
-    };
+    let canvas_size = canvas_extent(pixel_size);
 
     let mut canvas = Image {
         texture_descriptor: TextureDescriptor {
@@ -117,8 +231,34 @@ pub fn setup_cameras(
         ))
         .id();
 
+    for side in [Vec2::X, Vec2::NEG_X, Vec2::Y, Vec2::NEG_Y] {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: BAR_COLOR,
+                    ..default()
+                },
+                transform: Transform::from_xyz(0.0, 0.0, 1.0),
+                ..default()
+            },
+            Bar(side),
+            HIGH_RES_LAYERS,
+        ));
+    }
+
     let outer_camera = commands
-        .spawn((Camera2dBundle::default(), OuterCamera, HIGH_RES_LAYERS))
+        .spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    // the letterbox and pillarbox bars around the canvas
+                    clear_color: ClearColorConfig::Custom(BAR_COLOR),
+                    ..default()
+                },
+                ..default()
+            },
+            OuterCamera,
+            HIGH_RES_LAYERS,
+        ))
         .id();
     // Spawn a circle for the outer camera (high-res layer)
     commands.spawn((
@@ -138,80 +278,222 @@ pub fn setup_cameras(
         outer_camera,
         canvas: canvas_entity,
         canvas_image: image_handle.clone(),
-        // zoom: ZOOM,
+        zoom_step: DEFAULT_ZOOM_STEP,
+        zoom: ZOOM_STEPS[DEFAULT_ZOOM_STEP],
         pixel_size: PIXEL_SIZE,
+        scaling: SCALING,
+        canvas_scale,
+        position: Vec2::ZERO,
+        canvas_offset: Vec2::ZERO,
         followed_entity: None,
-        pixel_w,
-        pixel_h,
+        follow: FOLLOW,
+        clamp_to_map: true,
+        pixel_w: pixel_size.x,
+        pixel_h: pixel_size.y,
     });
 }
 
 fn follow(
-    camera: ResMut<CameraResource>,
-    mut transforms: Query<&mut Transform>,
+    time: Res<Time>,
+    gamepad_state: Res<gamepad::GamepadState>,
+    mut camera: ResMut<CameraResource>,
+    query: Query<(&Transform, Option<&motion::Movement>)>,
 ) {
-    let mut followed_transform: Option<Transform> = None;
-    if let Some(followed_entity) = camera.followed_entity {
-        if let Ok(transform) = transforms.get_mut(followed_entity) {
-            followed_transform = Some(transform.clone());
-        }
+    let Some(followed_entity) = camera.followed_entity else {
+        return;
+    };
+    let Ok((transform, movement)) = query.get(followed_entity) else {
+        return;
+    };
+    let follow = camera.follow;
+    let mut goal = transform.translation.truncate();
+    if let Some(movement) = movement {
+        goal += movement.velocity * follow.look_ahead;
     }
-    if let Some(transform) = followed_transform {
-        if let Ok(mut camera_transform) =
-            transforms.get_mut(camera.pixel_camera)
-        {
-            camera_transform.translation.x = transform.translation.x;
-            camera_transform.translation.y = transform.translation.y;
+    if gamepad_state.right_stick_deadzone_exceed {
+        goal +=
+            Vec2::new(gamepad_state.right_stick_x, gamepad_state.right_stick_y)
+                * follow.stick_look;
+    }
+    // only the part of the goal outside the dead zone moves the camera
+    let distance = goal - camera.position;
+    let outside =
+        distance.signum() * (distance.abs() - follow.dead_zone).max(Vec2::ZERO);
+    let catch_up = 1.0 - (-follow.smoothing * time.delta_seconds()).exp();
+    camera.position += outside * catch_up;
+}
+
+fn clamp_to_map(
+    mut camera: ResMut<CameraResource>,
+    bounds: Res<tilemap::MapBounds>,
+) {
+    if !camera.clamp_to_map {
+        return;
+    }
+    let half_size =
+        Vec2::new(camera.pixel_w as f32, camera.pixel_h as f32) / 2.0;
+    camera.position = bounds.clamp_view(camera.position, half_size);
+}
+
+// renders at whole game pixels so pixel art does not crawl, the canvas
+// takes the remainder in screen pixels which keeps scrolling smooth
+fn snap_camera(
+    camera: Res<CameraResource>,
+    mut in_game_camera_query: Query<
+        &mut Transform,
+        (With<InGameCamera>, Without<Canvas>),
+    >,
+    mut canvas_query: Query<&mut Transform, With<Canvas>>,
+) {
+    // an odd canvas has its center in the middle of a game pixel
+    let half = Vec2::new(
+        (camera.pixel_w % 2) as f32 / 2.0,
+        (camera.pixel_h % 2) as f32 / 2.0,
+    );
+    let snapped = (camera.position - half).round() + half;
+    if let Ok(mut transform) = in_game_camera_query.get_single_mut() {
+        transform.translation.x = snapped.x;
+        transform.translation.y = snapped.y;
+    }
+    if let Ok(mut transform) = canvas_query.get_single_mut() {
+        let offset = camera.canvas_offset
+            - (camera.position - snapped) * transform.scale.x;
+        transform.translation.x = offset.x;
+        transform.translation.y = offset.y;
+    }
+}
+
+// the triggers and the mouse wheel step through ZOOM_STEPS, fit_canvas
+// follows the animated zoom
+fn zoom(
+    time: Res<Time>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    mut wheel_events: EventReader<MouseWheel>,
+    mut camera: ResMut<CameraResource>,
+    mut scrolled: Local<f32>,
+) {
+    let mut step: i32 = 0;
+    for button in buttons.get_just_pressed() {
+        match button.button_type {
+            GamepadButtonType::RightTrigger2 => step += 1,
+            GamepadButtonType::LeftTrigger2 => step -= 1,
+            _ => {}
         }
     }
+    for event in wheel_events.read() {
+        *scrolled += match event.unit {
+            MouseScrollUnit::Line => event.y * WHEEL_STEP_PIXELS,
+            MouseScrollUnit::Pixel => event.y,
+        };
+    }
+    if scrolled.abs() >= WHEEL_STEP_PIXELS {
+        step += scrolled.signum() as i32;
+        *scrolled = 0.0;
+    }
+    camera.zoom_step = (camera.zoom_step as i32 + step.signum())
+        .clamp(0, ZOOM_STEPS.len() as i32 - 1) as usize;
+
+    let target = ZOOM_STEPS[camera.zoom_step];
+    if camera.zoom == target {
+        return;
+    }
+    let catch_up = 1.0 - (-ZOOM_SPEED * time.delta_seconds()).exp();
+    let zoom = camera.zoom + (target - camera.zoom) * catch_up;
+    camera.zoom = if (target - zoom).abs() < 0.01 {
+        target
+    } else {
+        zoom
+    };
 }
 
+// reapplies the scaling whenever it, the zoom or the window size changes
 fn fit_canvas(
-    mut resize_events: EventReader<WindowResized>,
+    windows: Query<&Window, With<PrimaryWindow>>,
     mut transforms: Query<&mut Transform, With<Canvas>>,
+    mut bars: Query<(&Bar, &mut Transform, &mut Sprite), Without<Canvas>>,
     mut images: ResMut<Assets<Image>>,
     mut camera_resource: ResMut<CameraResource>,
     mut in_game_camera_query: Query<
         &mut OrthographicProjection,
         With<InGameCamera>,
     >,
+    mut applied: Local<Option<(ScalingMode, f32, f32, UVec2)>>,
 ) {
-    for event in resize_events.read() {
-        let new_pixel_width =
-            (event.width / camera_resource.pixel_size as f32).floor() as u32;
-        let new_pixel_height =
-            (event.height / camera_resource.pixel_size as f32).floor() as u32;
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let window_size = window.physical_size();
+    let key = (
+        camera_resource.scaling,
+        camera_resource.pixel_size,
+        camera_resource.zoom,
+        window_size,
+    );
+    if *applied == Some(key) {
+        return;
+    }
+    *applied = Some(key);
+    let (pixel_size, canvas_scale) = camera_resource.scaling.fit(
+        window_size,
+        camera_resource.pixel_size,
+        camera_resource.zoom,
+    );
 
-        // Update the canvas size
+    // Update the canvas size, zooming often keeps it
+    let extent = canvas_extent(pixel_size);
+    if images
+        .get(&camera_resource.canvas_image)
+        .is_some_and(|image| image.texture_descriptor.size != extent)
+    {
         if let Some(canvas_image) =
             images.get_mut(&camera_resource.canvas_image)
         {
-            canvas_image.resize(Extent3d {
-                width: new_pixel_width,
-                height: new_pixel_height,
-                ..default()
-            });
+            canvas_image.resize(extent);
         }
+    }
 
-        // Update the camera resource
-        camera_resource.pixel_w = new_pixel_width;
-        camera_resource.pixel_h = new_pixel_height;
-
-        // Adjust the canvas transform to fill the window
-        if let Ok(mut transform) = transforms.get_single_mut() {
-            transform.scale = Vec3::new(
-                camera_resource.pixel_size as f32,
-                camera_resource.pixel_size as f32,
-                1.0,
-            );
-        }
+    // Update the camera resource
+    camera_resource.pixel_w = pixel_size.x;
+    camera_resource.pixel_h = pixel_size.y;
+    camera_resource.canvas_scale = canvas_scale;
 
-        // Adjust the in-game camera's orthographic projection
-        if let Ok(mut projection) = in_game_camera_query.get_single_mut() {
-            projection.area = Rect::from_center_size(
-                Vec2::ZERO,
-                Vec2::new(new_pixel_width as f32, new_pixel_height as f32),
-            );
-        }
+    // Scale the canvas, the outer camera works in logical pixels
+    if let Ok(mut transform) = transforms.get_single_mut() {
+        let scale_factor = window.scale_factor();
+        // whole scales stay pixel perfect when the canvas edges fall on
+        // screen pixel edges, an odd leftover moves it by half a pixel
+        let offset = if canvas_scale.fract() == 0.0 {
+            let leftover =
+                window_size.as_vec2() - pixel_size.as_vec2() * canvas_scale;
+            leftover.rem_euclid(Vec2::splat(2.0)) / 2.0
+        } else {
+            Vec2::ZERO
+        };
+        camera_resource.canvas_offset = offset / scale_factor;
+        transform.scale = Vec3::new(
+            canvas_scale / scale_factor,
+            canvas_scale / scale_factor,
+            1.0,
+        );
+    }
+
+    // Cover everything around the visible pixels, margin included
+    let half_visible =
+        pixel_size.as_vec2() * canvas_scale / window.scale_factor() / 2.0;
+    let bar_size = window.size().max_element() * 2.0;
+    for (bar, mut transform, mut sprite) in &mut bars {
+        let center = camera_resource.canvas_offset
+            + bar.0 * (half_visible + bar_size / 2.0);
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+        sprite.custom_size = Some(Vec2::splat(bar_size));
+    }
+
+    // Adjust the in-game camera's orthographic projection
+    if let Ok(mut projection) = in_game_camera_query.get_single_mut() {
+        projection.area = Rect::from_center_size(
+            Vec2::ZERO,
+            Vec2::new(extent.width as f32, extent.height as f32),
+        );
     }
 }
@@ -5,27 +5,22 @@ use iyes_perf_ui::entries::diagnostics::{
 };
 use iyes_perf_ui::ui::root::PerfUiRoot;
 use iyes_perf_ui::PerfUiPlugin;
+use std::time::Duration;
 
 const ANIMATION_FPS: f32 = 6.0;
 
-#[derive(Component)]
-struct AnimationIndices {
-    first: usize,
-    last: usize,
-}
-
 #[derive(Component, Deref, DerefMut)]
 struct AnimationTimer(Timer);
 
 pub struct DevPlugin;
 impl Plugin for DevPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((FrameTimeDiagnosticsPlugin, PerfUiPlugin::default()))
+        app.add_plugins((FrameTimeDiagnosticsPlugin, PerfUiPlugin))
             .add_systems(
                 Startup,
                 (spawn_lira.after(camera::setup_cameras), set_diagnostics),
             )
-            .add_systems(Update, animate_sprite);
+            .add_systems(Update, (animate_sprite, switch_scaling));
     }
 }
 
@@ -40,29 +35,22 @@ fn set_diagnostics(mut commands: Commands) {
 fn spawn_lira(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
     mut camera_resource: ResMut<camera::CameraResource>,
     mut controlled_entity: ResMut<motion::ControlledEntity>,
 ) {
-    let texture = asset_server.load("lira/idle.png");
-    let layout =
-        TextureAtlasLayout::from_grid(UVec2::new(75, 75), 8, 1, None, None);
-    let texture_atlas_layout = texture_atlas_layouts.add(layout);
-
-    let animation_indices = AnimationIndices { first: 0, last: 7 };
-
     let lira = commands
         .spawn((
             SpriteBundle {
-                texture,
                 transform: Transform::from_xyz(0., 0., 100.),
                 ..default()
             },
-            TextureAtlas {
-                layout: texture_atlas_layout,
-                index: animation_indices.first,
+            spritesheet::SheetSprite {
+                sheet: asset_server.load(assets::lira::idle::PATH),
+                index: 0,
             },
-            animation_indices,
+            spritesheet::SheetAnimation(
+                assets::lira::idle::Animation::Idle.name().into(),
+            ),
             AnimationTimer(Timer::from_seconds(
                 1.0 / ANIMATION_FPS,
                 TimerMode::Repeating,
@@ -79,19 +67,39 @@ fn spawn_lira(
 fn animate_sprite(
     time: Res<Time>,
     mut query: Query<(
-        &AnimationIndices,
+        &spritesheet::AnimationRange,
         &mut AnimationTimer,
-        &mut TextureAtlas,
+        &mut spritesheet::SheetSprite,
     )>,
 ) {
-    for (indices, mut timer, mut atlas) in &mut query {
+    for (range, mut timer, mut sprite) in &mut query {
+        // a hand edited sheet may hold any speed
+        let fps = range
+            .fps
+            .filter(|fps| fps.is_finite() && *fps > 0.0)
+            .unwrap_or(ANIMATION_FPS);
+        let duration = Duration::from_secs_f32(1.0 / fps);
+        if timer.duration() != duration {
+            timer.set_duration(duration);
+        }
         timer.tick(time.delta());
         if timer.just_finished() {
-            atlas.index = if atlas.index == indices.last {
-                indices.first
-            } else {
-                atlas.index + 1
-            };
+            if sprite.index != range.last {
+                sprite.index += 1;
+            } else if range.looping {
+                sprite.index = range.first;
+            }
         }
     }
 }
+
+// F2 cycles through the camera scaling modes
+fn switch_scaling(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut camera_resource: ResMut<camera::CameraResource>,
+) {
+    if keys.just_pressed(KeyCode::F2) {
+        camera_resource.scaling = camera_resource.scaling.next();
+        info!("camera scaling {:?}", camera_resource.scaling);
+    }
+}
@@ -0,0 +1,424 @@
+use flate2::read::ZlibDecoder;
+use image::{Rgba, RgbaImage};
+use std::io::Read;
+
+// reads aseprite files for forge, enough to flatten the visible layers
+// https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md
+const HEADER_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+const OLD_PALETTE_CHUNK: u16 = 0x0004;
+const LAYER_CHUNK: u16 = 0x2004;
+const CEL_CHUNK: u16 = 0x2005;
+const TAGS_CHUNK: u16 = 0x2018;
+const PALETTE_CHUNK: u16 = 0x2019;
+
+const LAYER_VISIBLE: u16 = 1;
+const LAYER_REFERENCE: u16 = 64;
+const GROUP_LAYER: u16 = 1;
+const LAYER_OPACITY_VALID: u32 = 1;
+const PALETTE_ENTRY_NAMED: u16 = 1;
+
+pub struct Document {
+    pub width: u32,
+    pub height: u32,
+    depth: u16,
+    transparent_index: u8,
+    palette: Vec<[u8; 4]>,
+    layers: Vec<Layer>,
+    pub frames: Vec<DocumentFrame>,
+    pub tags: Vec<Tag>,
+}
+
+struct Layer {
+    // false as well when a parent group is hidden
+    visible: bool,
+    opacity: u8,
+}
+
+pub struct DocumentFrame {
+    pub duration_ms: u16,
+    cels: Vec<Cel>,
+}
+
+struct Cel {
+    layer: usize,
+    x: i16,
+    y: i16,
+    opacity: u8,
+    z_index: i16,
+    content: CelContent,
+}
+
+enum CelContent {
+    Raw { w: u32, h: u32, data: Vec<u8> },
+    Compressed { w: u32, h: u32, data: Vec<u8> },
+    // same pixels as the cel of this layer in another frame
+    Linked(usize),
+}
+
+pub struct Tag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+}
+
+pub fn read(data: &[u8]) -> Result<Document, String> {
+    let mut bytes = Bytes { data, pos: 0 };
+    bytes.u32()?; // file size
+    if bytes.u16()? != HEADER_MAGIC {
+        return Err("not an aseprite file".into());
+    }
+    let frame_count = bytes.u16()?;
+    let width = bytes.u16()? as u32;
+    let height = bytes.u16()? as u32;
+    let depth = bytes.u16()?;
+    if !matches!(depth, 8 | 16 | 32) {
+        return Err(format!("unsupported color depth {}", depth));
+    }
+    let flags = bytes.u32()?;
+    bytes.skip(10)?; // speed and two zero dwords
+    let transparent_index = bytes.u8()?;
+    bytes.skip(3)?;
+    // older files write 0 for 256
+    let colors = match bytes.u16()? {
+        0 => 256,
+        colors => colors as usize,
+    };
+    bytes.skip(94)?; // pixel ratio, grid and reserved bytes
+                     // indexed pixels can't address more than 256 entries
+    let max_colors = if depth == 8 { 256 } else { colors };
+
+    let mut document = Document {
+        width,
+        height,
+        depth,
+        transparent_index,
+        palette: Vec::new(),
+        layers: Vec::new(),
+        frames: Vec::with_capacity(frame_count as usize),
+        tags: Vec::new(),
+    };
+    let mut old_palette = Vec::new();
+    // visibility of the open groups, indexed by child level
+    let mut groups: Vec<bool> = Vec::new();
+    for _ in 0..frame_count {
+        let frame_size = bytes.u32()? as usize;
+        let mut frame = Bytes {
+            data: bytes.take(frame_size.saturating_sub(4))?,
+            pos: 0,
+        };
+        if frame.u16()? != FRAME_MAGIC {
+            return Err("frame header is damaged".into());
+        }
+        let old_chunk_count = frame.u16()? as u32;
+        let duration_ms = frame.u16()?;
+        frame.skip(2)?;
+        let chunk_count = match frame.u32()? {
+            0 => old_chunk_count,
+            count => count,
+        };
+
+        let mut cels = Vec::new();
+        for _ in 0..chunk_count {
+            let chunk_size = frame.u32()? as usize;
+            let kind = frame.u16()?;
+            let mut chunk = Bytes {
+                data: frame.take(chunk_size.saturating_sub(6))?,
+                pos: 0,
+            };
+            match kind {
+                LAYER_CHUNK => {
+                    let layer_flags = chunk.u16()?;
+                    let layer_kind = chunk.u16()?;
+                    let level = chunk.u16()? as usize;
+                    chunk.skip(6)?; // default size and blend mode
+                    let opacity = chunk.u8()?;
+                    groups.truncate(level);
+                    let visible = layer_flags & LAYER_VISIBLE != 0
+                        && layer_flags & LAYER_REFERENCE == 0
+                        && groups.last().is_none_or(|&parent| parent);
+                    if layer_kind == GROUP_LAYER {
+                        groups.push(visible);
+                    }
+                    document.layers.push(Layer {
+                        visible,
+                        opacity: match flags & LAYER_OPACITY_VALID {
+                            0 => 255,
+                            _ => opacity,
+                        },
+                    });
+                }
+                CEL_CHUNK => {
+                    let layer = chunk.u16()? as usize;
+                    let x = chunk.i16()?;
+                    let y = chunk.i16()?;
+                    let opacity = chunk.u8()?;
+                    let cel_kind = chunk.u16()?;
+                    let z_index = chunk.i16()?;
+                    chunk.skip(5)?;
+                    let content = match cel_kind {
+                        0 => CelContent::Raw {
+                            w: chunk.u16()? as u32,
+                            h: chunk.u16()? as u32,
+                            data: chunk.rest().to_vec(),
+                        },
+                        1 => CelContent::Linked(chunk.u16()? as usize),
+                        2 => CelContent::Compressed {
+                            w: chunk.u16()? as u32,
+                            h: chunk.u16()? as u32,
+                            data: chunk.rest().to_vec(),
+                        },
+                        // tilemaps are not flattened
+                        _ => continue,
+                    };
+                    cels.push(Cel {
+                        layer,
+                        x,
+                        y,
+                        opacity,
+                        z_index,
+                        content,
+                    });
+                }
+                TAGS_CHUNK => {
+                    let count = chunk.u16()?;
+                    chunk.skip(8)?;
+                    for _ in 0..count {
+                        let from = chunk.u16()? as usize;
+                        let to = chunk.u16()? as usize;
+                        // direction, repeat, reserved bytes and color
+                        chunk.skip(13)?;
+                        let name = chunk.string()?;
+                        document.tags.push(Tag { name, from, to });
+                    }
+                }
+                PALETTE_CHUNK => {
+                    let size = chunk.u32()? as usize;
+                    let first = chunk.u32()? as usize;
+                    let last = chunk.u32()? as usize;
+                    chunk.skip(8)?;
+                    if first > last {
+                        return Err("palette chunk is damaged".into());
+                    }
+                    if size > max_colors || last >= max_colors {
+                        return Err(format!(
+                            "palette chunk has more than {} colors",
+                            max_colors
+                        ));
+                    }
+                    let palette = &mut document.palette;
+                    palette
+                        .resize(palette.len().max(size).max(last + 1), [0; 4]);
+                    for entry in &mut palette[first..=last] {
+                        let entry_flags = chunk.u16()?;
+                        let rgba = chunk.take(4)?;
+                        *entry = [rgba[0], rgba[1], rgba[2], rgba[3]];
+                        if entry_flags & PALETTE_ENTRY_NAMED != 0 {
+                            chunk.string()?;
+                        }
+                    }
+                }
+                OLD_PALETTE_CHUNK => {
+                    let packets = chunk.u16()?;
+                    let mut index = 0;
+                    for _ in 0..packets {
+                        index += chunk.u8()? as usize;
+                        let count = match chunk.u8()? {
+                            0 => 256,
+                            count => count as usize,
+                        };
+                        for _ in 0..count {
+                            let rgb = chunk.take(3)?;
+                            if index >= max_colors {
+                                return Err(format!(
+                                    "palette chunk has more than {} colors",
+                                    max_colors
+                                ));
+                            }
+                            if old_palette.len() <= index {
+                                old_palette.resize(index + 1, [0; 4]);
+                            }
+                            old_palette[index] = [rgb[0], rgb[1], rgb[2], 255];
+                            index += 1;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        document.frames.push(DocumentFrame { duration_ms, cels });
+    }
+    // older files only carry the old palette chunk
+    if document.palette.is_empty() {
+        document.palette = old_palette;
+    }
+    Ok(document)
+}
+
+impl Document {
+    // visible layers blended in order, blend modes all count as normal
+    pub fn render(&self, frame: usize) -> Result<RgbaImage, String> {
+        let mut image = RgbaImage::new(self.width, self.height);
+        let Some(frame) = self.frames.get(frame) else {
+            return Err(format!("there is no frame {}", frame));
+        };
+        let mut cels: Vec<&Cel> = frame
+            .cels
+            .iter()
+            .filter(|cel| {
+                self.layers
+                    .get(cel.layer)
+                    .is_some_and(|layer| layer.visible)
+            })
+            .collect();
+        // a z index moves the cel in front of or behind later layers
+        cels.sort_by_key(|cel| {
+            (cel.layer as i32 + cel.z_index as i32, cel.z_index)
+        });
+        let bytes_per_pixel = self.depth as usize / 8;
+        for cel in cels {
+            let (w, h, pixels) = self.cel_pixels(cel)?;
+            if pixels.len() < (w * h) as usize * bytes_per_pixel {
+                return Err(format!("cel on layer {} is cut short", cel.layer));
+            }
+            let opacity = cel.opacity as u32
+                * self.layers[cel.layer].opacity as u32
+                / 255;
+            for py in 0..h {
+                for px in 0..w {
+                    let x = cel.x as i64 + px as i64;
+                    let y = cel.y as i64 + py as i64;
+                    if x < 0
+                        || y < 0
+                        || x >= self.width as i64
+                        || y >= self.height as i64
+                    {
+                        continue;
+                    }
+                    let at = (py * w + px) as usize * bytes_per_pixel;
+                    let color = self.color(&pixels[at..at + bytes_per_pixel]);
+                    blend(
+                        image.get_pixel_mut(x as u32, y as u32),
+                        color,
+                        opacity,
+                    );
+                }
+            }
+        }
+        Ok(image)
+    }
+
+    // playback speed of a frame range from its frame durations
+    pub fn fps(&self, first: usize, last: usize) -> Option<f32> {
+        let frames = self.frames.get(first..=last)?;
+        let total: u32 =
+            frames.iter().map(|frame| frame.duration_ms as u32).sum();
+        (total > 0).then(|| 1000.0 * frames.len() as f32 / total as f32)
+    }
+
+    fn cel_pixels(&self, cel: &Cel) -> Result<(u32, u32, Vec<u8>), String> {
+        match &cel.content {
+            CelContent::Raw { w, h, data } => Ok((*w, *h, data.clone())),
+            CelContent::Compressed { w, h, data } => {
+                let mut pixels = Vec::new();
+                ZlibDecoder::new(&data[..])
+                    .read_to_end(&mut pixels)
+                    .map_err(|e| format!("cannot inflate cel: {}", e))?;
+                Ok((*w, *h, pixels))
+            }
+            CelContent::Linked(frame) => {
+                let original = self
+                    .frames
+                    .get(*frame)
+                    .and_then(|frame| {
+                        frame.cels.iter().find(|original| {
+                            original.layer == cel.layer
+                                && !matches!(
+                                    original.content,
+                                    CelContent::Linked(_)
+                                )
+                        })
+                    })
+                    .ok_or("linked cel points at a missing cel")?;
+                self.cel_pixels(original)
+            }
+        }
+    }
+
+    fn color(&self, pixel: &[u8]) -> [u8; 4] {
+        match pixel {
+            [r, g, b, a] => [*r, *g, *b, *a],
+            [value, alpha] => [*value, *value, *value, *alpha],
+            [index] if *index == self.transparent_index => [0; 4],
+            [index] => {
+                self.palette.get(*index as usize).copied().unwrap_or([0; 4])
+            }
+            _ => [0; 4],
+        }
+    }
+}
+
+// source over destination with straight alpha
+fn blend(target: &mut Rgba<u8>, color: [u8; 4], opacity: u32) {
+    let source_alpha = color[3] as u32 * opacity / 255;
+    if source_alpha == 0 {
+        return;
+    }
+    let target_alpha = target[3] as u32 * (255 - source_alpha) / 255;
+    let alpha = source_alpha + target_alpha;
+    for c in 0..3 {
+        target[c] = ((color[c] as u32 * source_alpha
+            + target[c] as u32 * target_alpha)
+            / alpha) as u8;
+    }
+    target[3] = alpha as u8;
+}
+
+// little endian reader over a byte slice
+struct Bytes<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+impl<'a> Bytes<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos + len;
+        let slice = self.data.get(self.pos..end).ok_or("file is cut short")?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.pos..];
+        self.pos = self.data.len();
+        rest
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), String> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16, String> {
+        self.u16().map(|value| value as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.u16()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| "name is not valid UTF-8".into())
+    }
+}
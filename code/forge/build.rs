@@ -0,0 +1,537 @@
+use crate::collect::{AnimationSource, FrameSource, SheetSource};
+use crate::layout::{self, Packing};
+use crate::manifest::{self, Fnv};
+use crate::options::AnimationConfig;
+use crate::palette::{self, SheetPalette};
+use crate::{sheet, Companion, Event, ForgeError, Issue, PackOptions, Palette};
+use image::{GenericImageView, RgbaImage};
+use rayon::prelude::*;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// a decoded frame on its way into a sheet
+pub struct Frame {
+    pub path: PathBuf,
+    // file name, with the frame number for layered files
+    pub source: String,
+    pub image: image::DynamicImage,
+    pub trim: Option<sheet::TrimMeta>,
+}
+
+// where the pixels of a frame end up after deduplication
+#[derive(Clone, Copy)]
+enum Origin {
+    // index into the frames packed by this sheet
+    Packed(usize),
+    // an earlier identical frame of this sheet
+    Duplicate(usize),
+    // a frame packed by an earlier sheet of the directory
+    Shared(usize),
+}
+
+// frames packed by earlier sheets of a directory with dedupe_across
+#[derive(Default)]
+pub struct SharedFrames(Vec<SharedFrame>);
+impl SharedFrames {
+    fn find(&self, hash: u64, image: &image::DynamicImage) -> Option<usize> {
+        self.0.iter().position(|shared| {
+            shared.hash == hash && same_pixels(&shared.image, image)
+        })
+    }
+}
+
+struct SharedFrame {
+    hash: u64,
+    image: image::DynamicImage,
+    page: sheet::PageMeta,
+    rect: sheet::FrameRect,
+}
+
+// a sheet in memory, the file names in meta are where forge would save it
+pub struct PackedSheet {
+    pub pages: Vec<RgbaImage>,
+    // pages of every companion, laid out like the sheet pages
+    pub companions: Vec<(Companion, Vec<RgbaImage>)>,
+    // base palette and swaps as rows, for indexed pages
+    pub lookup: Option<RgbaImage>,
+    pub meta: sheet::SheetMeta,
+}
+
+// frames in order as one animation named after the sheet, a palette in the
+// options is checked and applied like for a directory
+pub fn pack(
+    name: &str,
+    images: Vec<RgbaImage>,
+    options: &PackOptions,
+) -> Result<PackedSheet, ForgeError> {
+    if images.is_empty() {
+        return Err(ForgeError::Pack(format!("{} has no frames", name)));
+    }
+    let frames: Vec<Frame> = images
+        .into_iter()
+        .enumerate()
+        .map(|(i, image)| {
+            let source = format!("{}_{}", name, i);
+            Frame {
+                path: PathBuf::from(&source),
+                source,
+                image: image::DynamicImage::ImageRgba8(image),
+                trim: None,
+            }
+        })
+        .collect();
+    let palette = match &options.palette {
+        Some(path) => {
+            let base = Palette::load(path).map_err(|message| {
+                ForgeError::Invalid(vec![Issue {
+                    path: path.clone(),
+                    message,
+                }])
+            })?;
+            let issues: Vec<_> = frames
+                .iter()
+                .filter_map(|frame| palette::check_palette(frame, &base))
+                .collect();
+            if !issues.is_empty() {
+                return Err(ForgeError::OffPalette(issues));
+            }
+            Some(SheetPalette {
+                base,
+                swaps: Vec::new(),
+            })
+        }
+        None => None,
+    };
+    let animations = [AnimationSource {
+        name: name.to_string(),
+        first: 0,
+        last: frames.len() - 1,
+        config: AnimationConfig::default(),
+    }];
+    pack_frames(
+        name,
+        frames,
+        options,
+        &animations,
+        &[],
+        palette.as_ref(),
+        &mut SharedFrames::default(),
+    )
+}
+
+fn pack_frames(
+    name: &str,
+    mut frames: Vec<Frame>,
+    options: &PackOptions,
+    animations: &[AnimationSource],
+    directions: &[&'static str],
+    palette: Option<&SheetPalette>,
+    shared: &mut SharedFrames,
+) -> Result<PackedSheet, ForgeError> {
+    if options.trim {
+        frames.iter_mut().for_each(trim_frame);
+    }
+
+    let hashes: Vec<u64> = frames
+        .iter()
+        .map(|frame| pixel_hash(&frame.image))
+        .collect();
+    let mut origins = Vec::with_capacity(frames.len());
+    let mut packed = Vec::new();
+    // frames of a facing keep their grid row, so those are never shared
+    let dedupe = directions.is_empty();
+    for (i, frame) in frames.iter().enumerate() {
+        let duplicate = (0..i).filter(|_| dedupe).find(|&j| {
+            hashes[j] == hashes[i]
+                && same_pixels(&frames[j].image, &frame.image)
+        });
+        let origin = if let Some(j) = duplicate {
+            Origin::Duplicate(j)
+        } else if let Some(s) =
+            shared.find(hashes[i], &frame.image).filter(|_| dedupe)
+        {
+            Origin::Shared(s)
+        } else {
+            packed.push(i);
+            Origin::Packed(packed.len() - 1)
+        };
+        origins.push(origin);
+    }
+
+    // packed slots include the extruded border, rects stay the inner frame
+    let extrude = options.extrude;
+    let sizes: Vec<(u32, u32)> = packed
+        .iter()
+        .map(|&i| {
+            let (w, h) = frames[i].image.dimensions();
+            (w + 2 * extrude, h + 2 * extrude)
+        })
+        .collect();
+    let packing = match options.layout {
+        sheet::SheetLayout::Grid => {
+            let row_length = (!directions.is_empty())
+                .then(|| (frames.len() / directions.len()) as u32);
+            layout::pack_grid(&sizes, options, row_length)?
+        }
+        sheet::SheetLayout::Shelf => layout::pack_shelf(&sizes, options)?,
+    };
+
+    let mut pages = layout::compose_pages(
+        &packing,
+        packed.iter().map(|&i| &frames[i].image),
+        extrude,
+    );
+    let palette = palette.filter(|_| options.indexed);
+    if let Some(palette) = palette {
+        for pixel in pages.iter_mut().flat_map(|page| page.pixels_mut()) {
+            let index = palette.base.index([pixel[0], pixel[1], pixel[2]]);
+            *pixel = match (pixel[3], index) {
+                (0, _) | (_, None) => image::Rgba([0; 4]),
+                (alpha, Some(index)) => image::Rgba([index as u8, 0, 0, alpha]),
+            };
+        }
+    }
+    let companions = options
+        .companions
+        .iter()
+        .map(|&companion| {
+            let images: Vec<_> = packed
+                .iter()
+                .map(|&i| companion.render(&frames[i].image))
+                .collect();
+            (companion, layout::compose_pages(&packing, &images, extrude))
+        })
+        .collect();
+
+    let mut page_metas: Vec<sheet::PageMeta> = packing
+        .pages
+        .iter()
+        .enumerate()
+        .map(|(i, &(w, h))| sheet::PageMeta {
+            image: page_file_name(name, i),
+            w,
+            h,
+            companions: options
+                .companions
+                .iter()
+                .map(|&companion| {
+                    (
+                        companion.name().to_string(),
+                        companion_file_name(name, i, companion),
+                    )
+                })
+                .collect(),
+        })
+        .collect();
+    let mut frame_metas: Vec<sheet::FrameMeta> = Vec::new();
+    for (frame, &origin) in frames.iter().zip(&origins) {
+        let (page, rect, duplicate_of) = match origin {
+            Origin::Packed(p) => {
+                let (page, x, y) = packing.placements[p];
+                let rect = sheet::FrameRect {
+                    x: x + extrude,
+                    y: y + extrude,
+                    w: frame.image.width(),
+                    h: frame.image.height(),
+                };
+                (page, rect, None)
+            }
+            Origin::Duplicate(j) => {
+                (frame_metas[j].page, frame_metas[j].rect, Some(j))
+            }
+            Origin::Shared(s) => {
+                let shared = &shared.0[s];
+                let page = match page_metas
+                    .iter()
+                    .position(|page| *page == shared.page)
+                {
+                    Some(page) => page,
+                    None => {
+                        page_metas.push(shared.page.clone());
+                        page_metas.len() - 1
+                    }
+                };
+                (page, shared.rect, None)
+            }
+        };
+        frame_metas.push(sheet::FrameMeta {
+            source: frame.source.clone(),
+            page,
+            rect,
+            trim: frame.trim,
+            duplicate_of,
+        });
+    }
+
+    if options.dedupe_across {
+        for &i in &packed {
+            let meta = &frame_metas[i];
+            shared.0.push(SharedFrame {
+                hash: hashes[i],
+                image: frames[i].image.clone(),
+                page: page_metas[meta.page].clone(),
+                rect: meta.rect,
+            });
+        }
+    }
+
+    let mut meta = sheet_meta(
+        options,
+        animations,
+        directions,
+        &frames,
+        page_metas,
+        frame_metas,
+        &packing,
+    );
+    let lookup = palette.map(|palette| {
+        meta.palette = Some(sheet::PaletteMeta {
+            image: format!("{}.palette.png", name),
+            colors: palette.base.colors.len() as u32,
+            swaps: palette.swaps.iter().map(|(name, _)| name.clone()).collect(),
+        });
+        lookup_image(palette)
+    });
+    Ok(PackedSheet {
+        pages,
+        companions,
+        lookup,
+        meta,
+    })
+}
+
+// events of one sheet are reported together once all its files are written
+pub fn build_sheet(
+    source: &SheetSource,
+    shared: &mut SharedFrames,
+    on_event: &Mutex<impl FnMut(Event)>,
+) -> Result<(), ForgeError> {
+    fs::create_dir_all(&source.output_dir)?;
+    let mut sheet = pack_frames(
+        &source.name,
+        decode_frames(source)?,
+        &source.options,
+        &source.animations,
+        &source.directions,
+        source.palette.as_ref(),
+        shared,
+    )?;
+    sheet.meta.tiles = source.tiles();
+    let meta_path = source.meta_path();
+    let previous = manifest::sheet_outputs(&meta_path).unwrap_or_default();
+
+    let mut generated = Vec::new();
+    for (page, meta) in sheet.pages.iter().zip(&sheet.meta.pages) {
+        let output_path = source.output_dir.join(&meta.image);
+        page.save(&output_path)?;
+        generated.push(("spritesheet", output_path));
+    }
+    for (companion, pages) in &sheet.companions {
+        for (page, meta) in pages.iter().zip(&sheet.meta.pages) {
+            let output_path =
+                source.output_dir.join(&meta.companions[companion.name()]);
+            page.save(&output_path)?;
+            generated.push(("companion", output_path));
+        }
+    }
+    if let (Some(lookup), Some(palette)) = (&sheet.lookup, &sheet.meta.palette)
+    {
+        let output_path = source.output_dir.join(&palette.image);
+        lookup.save(&output_path)?;
+        generated.push(("palette lookup", output_path));
+    }
+    let pretty = ron::ser::PrettyConfig::default();
+    fs::write(&meta_path, ron::ser::to_string_pretty(&sheet.meta, pretty)?)?;
+    // pages of a sheet that shrank
+    let mut removed = Vec::new();
+    for path in previous {
+        if path != meta_path
+            && !generated.iter().any(|(_, generated)| *generated == path)
+            && path.exists()
+        {
+            fs::remove_file(&path)?;
+            removed.push(path);
+        }
+    }
+
+    let mut on_event = on_event.lock().unwrap();
+    for (kind, path) in &generated {
+        on_event(Event::Generated { kind, path });
+    }
+    for frame in &sheet.meta.frames {
+        on_event(Event::Placed {
+            frame: &frame.source,
+            page: &sheet.meta.pages[frame.page].image,
+            x: frame.rect.x,
+            y: frame.rect.y,
+        });
+    }
+    on_event(Event::Generated {
+        kind: "metadata",
+        path: &meta_path,
+    });
+    for path in &removed {
+        on_event(Event::Removed { path });
+    }
+    Ok(())
+}
+
+// frames of one sheet are decoded in parallel, in their order
+fn decode_frames(source: &SheetSource) -> Result<Vec<Frame>, ForgeError> {
+    source
+        .frames
+        .par_iter()
+        .map(|frame| decode_frame(source, frame))
+        .collect()
+}
+
+pub fn decode_frame(
+    source: &SheetSource,
+    frame: &FrameSource,
+) -> Result<Frame, ForgeError> {
+    let file_name = frame
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    // one pixel format so identical frames compare byte for byte
+    let (image, label) = match &source.layered {
+        Some(document) => (
+            document
+                .render(frame.number as usize)
+                .map_err(ForgeError::Layered)?,
+            format!("{}#{}", file_name, frame.number),
+        ),
+        None => (image::open(&frame.path)?.into_rgba8(), file_name),
+    };
+    Ok(Frame {
+        image: image::DynamicImage::ImageRgba8(image),
+        path: frame.path.clone(),
+        source: label,
+        trim: None,
+    })
+}
+
+fn pixel_hash(image: &image::DynamicImage) -> u64 {
+    let mut hasher = Fnv::default();
+    image.dimensions().hash(&mut hasher);
+    hasher.write(image.as_bytes());
+    hasher.finish()
+}
+
+fn same_pixels(a: &image::DynamicImage, b: &image::DynamicImage) -> bool {
+    a.dimensions() == b.dimensions() && a.as_bytes() == b.as_bytes()
+}
+
+// row 0 holds the base palette, every swap adds a row below it
+fn lookup_image(palette: &SheetPalette) -> RgbaImage {
+    let rows = std::iter::once(&palette.base)
+        .chain(palette.swaps.iter().map(|(_, swap)| swap));
+    let mut lookup = RgbaImage::new(
+        palette.base.colors.len() as u32,
+        1 + palette.swaps.len() as u32,
+    );
+    for (y, row) in rows.enumerate() {
+        for (x, &[r, g, b]) in row.colors.iter().enumerate() {
+            lookup.put_pixel(x as u32, y as u32, image::Rgba([r, g, b, 255]));
+        }
+    }
+    lookup
+}
+
+// first page keeps the plain name so existing asset paths stay valid
+fn page_file_name(sprite_name: &str, page: usize) -> String {
+    if page == 0 {
+        format!("{}.png", sprite_name)
+    } else {
+        format!("{}.{}.png", sprite_name, page)
+    }
+}
+
+// idle.outline.png next to idle.png, idle.1.outline.png next to idle.1.png
+fn companion_file_name(
+    sprite_name: &str,
+    page: usize,
+    companion: Companion,
+) -> String {
+    let page_name = page_file_name(sprite_name, page);
+    let stem = page_name.trim_end_matches(".png");
+    format!("{}.{}.png", stem, companion.name())
+}
+
+// crops to the opaque bounding box and remembers where it was
+fn trim_frame(frame: &mut Frame) {
+    let (source_w, source_h) = frame.image.dimensions();
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for (x, y, pixel) in frame.image.pixels() {
+        if pixel[3] > 0 {
+            bounds = Some(match bounds {
+                Some((x0, y0, x1, y1)) => {
+                    (x0.min(x), y0.min(y), x1.max(x), y1.max(y))
+                }
+                None => (x, y, x, y),
+            });
+        }
+    }
+    // fully transparent frames keep a single pixel
+    let (x0, y0, x1, y1) = bounds.unwrap_or((0, 0, 0, 0));
+    frame.image = frame.image.crop_imm(x0, y0, x1 - x0 + 1, y1 - y0 + 1);
+    frame.trim = Some(sheet::TrimMeta {
+        x: x0,
+        y: y0,
+        source_w,
+        source_h,
+    });
+}
+
+fn sheet_meta(
+    options: &PackOptions,
+    animations: &[AnimationSource],
+    directions: &[&'static str],
+    frames: &[Frame],
+    pages: Vec<sheet::PageMeta>,
+    frame_metas: Vec<sheet::FrameMeta>,
+    packing: &Packing,
+) -> sheet::SheetMeta {
+    sheet::SheetMeta {
+        layout: options.layout,
+        extrude: options.extrude,
+        pages,
+        frame_w: frames
+            .iter()
+            .map(|frame| frame.image.width())
+            .max()
+            .unwrap_or(0),
+        frame_h: frames
+            .iter()
+            .map(|frame| frame.image.height())
+            .max()
+            .unwrap_or(0),
+        frame_count: frames.len(),
+        columns: packing.columns,
+        rows: packing.rows,
+        frames: frame_metas,
+        directions: directions
+            .iter()
+            .enumerate()
+            .map(|(row, &direction)| sheet::DirectionMeta {
+                name: direction.to_string(),
+                row: row as u32,
+            })
+            .collect(),
+        palette: None,
+        tiles: Vec::new(),
+        animations: animations
+            .iter()
+            .map(|animation| sheet::AnimationMeta {
+                name: animation.name.clone(),
+                first: animation.first,
+                last: animation.last,
+                fps: animation.config.fps,
+                looping: animation.config.looping,
+            })
+            .collect(),
+    }
+}
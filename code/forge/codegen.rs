@@ -0,0 +1,234 @@
+use crate::{sheet, ForgeError};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+// strict and reserved keywords of every edition, gen is from 2024
+const KEYWORDS: [&str; 51] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const",
+    "continue", "crate", "do", "dyn", "else", "enum", "extern", "false",
+    "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro",
+    "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+// a module per directory of the assets, sheets are modules inside them
+#[derive(Default)]
+struct Module {
+    modules: BTreeMap<String, Module>,
+    sheets: BTreeMap<String, (String, sheet::SheetMeta)>,
+}
+
+// rust source with a module per sheet below assets_dir, holding its PATH
+// and enums of its animations and tiles with their frame ranges
+pub fn rust_manifest(assets_dir: &Path) -> Result<String, ForgeError> {
+    let suffix = format!(".{}", sheet::SHEET_EXTENSION);
+    let mut root = Module::default();
+    let mut metas: Vec<_> = WalkDir::new(assets_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().ends_with(&suffix))
+        .map(|e| e.into_path())
+        .collect();
+    metas.sort();
+    for path in metas {
+        let relative = path.strip_prefix(assets_dir).unwrap_or(&path);
+        let parts: Vec<_> = relative
+            .iter()
+            .map(|part| part.to_string_lossy().into_owned())
+            .collect();
+        let Some((file, dirs)) = parts.split_last() else {
+            continue;
+        };
+        let meta: sheet::SheetMeta = ron::from_str(&fs::read_to_string(&path)?)
+            .map_err(|e| {
+                ForgeError::Codegen(format!("{}: {}", path.display(), e))
+            })?;
+        let mut module = &mut root;
+        for dir in dirs {
+            module = module.modules.entry(module_name(dir)).or_default();
+        }
+        let name = module_name(file.trim_end_matches(&suffix));
+        if module.sheets.contains_key(&name) {
+            return Err(ForgeError::Codegen(format!(
+                "{} is a second sheet named {}",
+                relative.display(),
+                name
+            )));
+        }
+        module.sheets.insert(name, (parts.join("/"), meta));
+    }
+
+    Ok(format!(
+        "// generated by forge from the sheets in the assets, do not edit\n\
+         #![allow(dead_code, non_camel_case_types)]\n{}",
+        module_source(&root)?
+    ))
+}
+
+fn module_source(module: &Module) -> Result<String, ForgeError> {
+    let mut source = String::new();
+    for (name, (path, meta)) in &module.sheets {
+        if module.modules.contains_key(name) {
+            return Err(ForgeError::Codegen(format!(
+                "{} is both a sheet and a directory",
+                path
+            )));
+        }
+        let animations: Vec<_> = meta
+            .animations
+            .iter()
+            .map(|a| (a.name.as_str(), format!("{}..{}", a.first, a.last + 1)))
+            .collect();
+        let tiles: Vec<_> = meta
+            .tiles
+            .iter()
+            .map(|t| {
+                (
+                    t.name.as_str(),
+                    format!("{}..{}", t.first, t.first + t.variants),
+                )
+            })
+            .collect();
+        let body = format!(
+            "pub const PATH: &str = {:?};\npub const FRAME_COUNT: usize = {};\n{}{}",
+            path,
+            meta.frame_count,
+            enum_source(path, "Animation", &animations)?,
+            enum_source(path, "Tile", &tiles)?,
+        );
+        source.push_str(&format!(
+            "\npub mod {} {{\n{}}}\n",
+            name,
+            indent(body.trim_start())
+        ));
+    }
+    for (name, child) in &module.modules {
+        let body = module_source(child)?;
+        source.push_str(&format!(
+            "\npub mod {} {{\n{}}}\n",
+            name,
+            indent(body.trim_start())
+        ));
+    }
+    Ok(source)
+}
+
+// frame ranges are half open like Spritesheet::tiles, an animation's ends
+// one past its last frame in the metadata
+fn enum_source(
+    path: &str,
+    kind: &str,
+    entries: &[(&str, String)],
+) -> Result<String, ForgeError> {
+    if entries.is_empty() {
+        return Ok(String::new());
+    }
+    let mut variants: Vec<String> = Vec::new();
+    for (name, _) in entries {
+        let variant = variant_name(name);
+        if variants.contains(&variant) {
+            return Err(ForgeError::Codegen(format!(
+                "{} has two {} entries named {}",
+                path,
+                kind.to_lowercase(),
+                variant
+            )));
+        }
+        variants.push(variant);
+    }
+    let arms = |arm: &dyn Fn(&(&str, String)) -> String| -> String {
+        variants
+            .iter()
+            .zip(entries)
+            .map(|(variant, entry)| {
+                format!("            Self::{} => {},\n", variant, arm(entry))
+            })
+            .collect()
+    };
+    let all: Vec<_> = variants.iter().map(|v| format!("Self::{}", v)).collect();
+    Ok(format!(
+        "
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum {kind} {{
+{variants}}}
+impl {kind} {{
+    pub const ALL: [Self; {count}] = [{all}];
+
+    pub fn name(self) -> &'static str {{
+        match self {{
+{names}        }}
+    }}
+
+    pub fn frames(self) -> std::ops::Range<usize> {{
+        match self {{
+{frames}        }}
+    }}
+}}
+",
+        kind = kind,
+        variants = variants
+            .iter()
+            .map(|variant| format!("    {},\n", variant))
+            .collect::<String>(),
+        count = variants.len(),
+        all = all.join(", "),
+        names = arms(&|(name, _)| format!("{:?}", name)),
+        frames = arms(&|(_, frames)| frames.clone()),
+    ))
+}
+
+// four spaces before every line that has text
+fn indent(source: &str) -> String {
+    source
+        .lines()
+        .map(|line| match line {
+            "" => "\n".to_string(),
+            line => format!("    {}\n", line),
+        })
+        .collect()
+}
+
+// lira for lira, walk_se for walk-se, move_ for move, __ for -
+fn module_name(name: &str) -> String {
+    let mut ident: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    // _ alone is a placeholder, not a name
+    if KEYWORDS.contains(&ident.as_str()) || ident == "_" {
+        ident.push('_');
+    }
+    ident
+}
+
+// WalkSe for walk_se, _1h for 1h, Self_ for self, __ for -
+fn variant_name(name: &str) -> String {
+    let mut ident: String = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().unwrap_or_default().to_ascii_uppercase();
+            std::iter::once(first).chain(chars).collect::<String>()
+        })
+        .collect();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if ident == "Self" || ident == "_" {
+        ident.push('_');
+    }
+    ident
+}
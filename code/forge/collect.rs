@@ -0,0 +1,581 @@
+use crate::aseprite;
+use crate::options::{AnimationConfig, DirConfig, PackOptions, PackOverrides};
+use crate::palette::{Palette, SheetPalette};
+use crate::{sheet, Issue, CONFIG_FILE, MANIFEST_FILE};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+// facings of directional sheets, also the order of their grid rows
+pub const DIRECTIONS: [&str; 8] = ["n", "ne", "e", "se", "s", "sw", "w", "nw"];
+
+// a sheet that passed validation, frames are not decoded yet
+pub struct SheetSource {
+    // directory below the input root, empty for the root itself
+    pub dir: PathBuf,
+    pub output_dir: PathBuf,
+    pub name: String,
+    pub frames: Vec<FrameSource>,
+    // defaults with the directory config and command line flags applied
+    pub options: PackOptions,
+    pub animations: Vec<AnimationSource>,
+    // one grid row each in this order, with an equal number of frames
+    pub directions: Vec<&'static str>,
+    pub palette: Option<SheetPalette>,
+    // set when all frames come from one aseprite file
+    pub layered: Option<aseprite::Document>,
+}
+impl SheetSource {
+    // dir/name with forward slashes, used by filters and the manifest
+    pub fn key(&self) -> String {
+        let mut key = String::new();
+        for part in self.dir.iter() {
+            key.push_str(&part.to_string_lossy());
+            key.push('/');
+        }
+        key.push_str(&self.name);
+        key
+    }
+
+    pub fn meta_path(&self) -> PathBuf {
+        self.output_dir.join(format!(
+            "{}.{}",
+            self.name,
+            sheet::SHEET_EXTENSION
+        ))
+    }
+
+    // the name to frame table of a tileset, empty for other sheets
+    pub fn tiles(&self) -> Vec<sheet::TileMeta> {
+        let mut tiles: Vec<sheet::TileMeta> = Vec::new();
+        for (i, frame) in self.frames.iter().enumerate() {
+            let Some(name) = &frame.tile else {
+                continue;
+            };
+            match tiles.last_mut() {
+                Some(tile) if tile.name == *name => tile.variants += 1,
+                _ => tiles.push(sheet::TileMeta {
+                    name: name.clone(),
+                    first: i,
+                    variants: 1,
+                }),
+            }
+        }
+        tiles
+    }
+}
+
+pub struct FrameSource {
+    pub path: PathBuf,
+    pub number: u32,
+    pub size: (u32, u32),
+    // walk_se_03 faces "se", None for sheets without facings
+    pub direction: Option<&'static str>,
+    // water for water_1.png in a tileset, None for animation frames
+    pub tile: Option<String>,
+}
+
+#[derive(Hash)]
+pub struct AnimationSource {
+    pub name: String,
+    pub first: usize,
+    pub last: usize,
+    pub config: AnimationConfig,
+}
+
+// frames are grouped by the directory they sit in, at any depth
+pub fn collect_sheets(
+    forge_dir: &Path,
+    assets_dir: &Path,
+    options: &PackOptions,
+    overrides: &PackOverrides,
+) -> (Vec<SheetSource>, Vec<Issue>) {
+    let mut groups: HashMap<(PathBuf, String), Vec<FrameSource>> =
+        HashMap::new();
+    let mut configs: HashMap<PathBuf, DirConfig> = HashMap::new();
+    let mut layered: HashMap<(PathBuf, String), aseprite::Document> =
+        HashMap::new();
+    let mut palettes: HashMap<PathBuf, Option<Palette>> = HashMap::new();
+    let mut issues = Vec::new();
+
+    let files: Vec<PathBuf> = WalkDir::new(forge_dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.file_name() != MANIFEST_FILE)
+        .map(|e| e.into_path())
+        .collect();
+    // headers and aseprite files are read on every core, grouping is not
+    let inputs: Vec<_> =
+        files.par_iter().map(|path| read_input(path)).collect();
+    let mut pngs = Vec::new();
+    for (path, input) in files.into_iter().zip(inputs) {
+        let dir = path
+            .parent()
+            .and_then(|parent| parent.strip_prefix(forge_dir).ok())
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+        match input {
+            Ok(Input::Config(config)) => {
+                configs.insert(dir, config);
+            }
+            Ok(Input::Layered(name, frames, document)) => {
+                let key = (dir, name);
+                groups.entry(key.clone()).or_default().extend(frames);
+                layered.insert(key, document);
+            }
+            Ok(Input::Png(size)) => pngs.push((dir, path, size)),
+            Err(message) => issues.push(Issue { path, message }),
+        }
+    }
+    // names depend on the config, so pngs are grouped once all are known
+    for (dir, path, size) in pngs {
+        let tileset = dir
+            .ancestors()
+            .find_map(|dir| configs.get(dir))
+            .is_some_and(|config| config.tileset);
+        let source = if tileset {
+            tile_source(&dir, &path, size)
+        } else {
+            frame_source(&path, size).map(|(name, frame)| ((dir, name), frame))
+        };
+        match source {
+            Ok((key, frame)) => groups.entry(key).or_default().push(frame),
+            Err(message) => issues.push(Issue { path, message }),
+        }
+    }
+
+    let mut sheets = Vec::new();
+    for ((dir, name), mut frames) in groups {
+        // numeric order, idle_2 before idle_10 and idle_02 alike
+        let row = |frame: &FrameSource| {
+            DIRECTIONS
+                .iter()
+                .position(|&known| Some(known) == frame.direction)
+        };
+        frames.sort_by_key(|frame| {
+            (
+                frame.tile.clone(),
+                row(frame),
+                frame.number,
+                frame.path.clone(),
+            )
+        });
+        // a tileset sheet sits beside its directory, the config is inside
+        let frames_dir = frames[0]
+            .path
+            .parent()
+            .and_then(|parent| parent.strip_prefix(forge_dir).ok())
+            .unwrap_or(Path::new(""));
+        let config = frames_dir.ancestors().find_map(|dir| configs.get(dir));
+        let mut sheet_options = options.clone();
+        let mut frame_size = None;
+        if let Some(config) = config {
+            config.apply(&mut sheet_options);
+            frame_size = config.frame_size;
+        }
+        overrides.apply(&mut sheet_options);
+
+        let mut directions: Vec<&'static str> = Vec::new();
+        for frame in &frames {
+            if let Some(direction) = frame.direction {
+                if directions.last() != Some(&direction) {
+                    directions.push(direction);
+                }
+            }
+        }
+        let tileset = frames.iter().any(|frame| frame.tile.is_some());
+        if tileset {
+            if frames.iter().any(|frame| frame.tile.is_none()) {
+                issues.push(Issue {
+                    path: forge_dir.join(&dir).join(&name),
+                    message: "sheet mixes tiles and animation frames".into(),
+                });
+            }
+            for tile in frames.chunk_by(|a, b| a.tile == b.tile) {
+                issues.extend(check_frames(tile, frame_size));
+                if tile[0].size != frames[0].size {
+                    issues.push(Issue {
+                        path: tile[0].path.clone(),
+                        message: format!(
+                            "tile is {}x{}, expected {}x{} like {}",
+                            tile[0].size.0,
+                            tile[0].size.1,
+                            frames[0].size.0,
+                            frames[0].size.1,
+                            frames[0].path.display()
+                        ),
+                    });
+                }
+            }
+        } else if directions.is_empty() {
+            issues.extend(check_frames(&frames, frame_size));
+        } else if frames.iter().any(|frame| frame.direction.is_none()) {
+            issues.push(Issue {
+                path: forge_dir.join(&dir).join(&name),
+                message: "sheet mixes frames with and without a direction"
+                    .into(),
+            });
+        } else if !matches!(sheet_options.layout, sheet::SheetLayout::Grid) {
+            // only a grid keeps every facing on a row of its own
+            issues.push(Issue {
+                path: forge_dir.join(&dir).join(&name),
+                message: "sheet has directions, they need the grid layout"
+                    .into(),
+            });
+        } else {
+            let rows: Vec<_> =
+                frames.chunk_by(|a, b| a.direction == b.direction).collect();
+            for (row, direction) in rows.iter().zip(&directions) {
+                issues.extend(check_frames(row, frame_size));
+                if row.len() != rows[0].len() {
+                    issues.push(Issue {
+                        path: forge_dir.join(&dir).join(&name),
+                        message: format!(
+                            "direction {} has {} frames, {} has {}",
+                            direction,
+                            row.len(),
+                            directions[0],
+                            rows[0].len()
+                        ),
+                    });
+                }
+            }
+        }
+        let document = layered.remove(&(dir.clone(), name.clone()));
+        // a lone frame is most often a misspelled name of another sheet
+        if document.is_none() && !tileset && frames.len() == 1 {
+            issues.push(Issue {
+                path: frames[0].path.clone(),
+                message: format!("only frame of {}, is the name a typo?", name),
+            });
+        }
+        if let Some(document) = &document {
+            if frames.len() != document.frames.len() {
+                issues.push(Issue {
+                    path: forge_dir.join(&dir).join(&name),
+                    message: "sheet has both an aseprite file and png frames"
+                        .into(),
+                });
+            }
+        }
+
+        // configured speed wins over the aseprite frame durations
+        // tags and facings without a config of their own use the sheet's
+        let animation = |animation_name: &str, first: usize, last: usize| {
+            let configured = config.and_then(|config| {
+                config
+                    .animations
+                    .get(animation_name)
+                    .or_else(|| config.animations.get(&name))
+            });
+            let config = match (configured, &document) {
+                (Some(&config), _) => config,
+                (None, Some(document)) => AnimationConfig {
+                    fps: document.fps(first, last),
+                    ..Default::default()
+                },
+                (None, None) => AnimationConfig::default(),
+            };
+            AnimationSource {
+                name: animation_name.to_string(),
+                first,
+                last,
+                config,
+            }
+        };
+        let mut animations = Vec::new();
+        for tag in document.iter().flat_map(|document| &document.tags) {
+            if tag.from > tag.to || tag.to >= frames.len() {
+                issues.push(Issue {
+                    path: forge_dir.join(&dir).join(&name),
+                    message: format!(
+                        "tag {} covers frames {} to {} of {}",
+                        tag.name,
+                        tag.from,
+                        tag.to,
+                        frames.len()
+                    ),
+                });
+                continue;
+            }
+            animations.push(animation(&tag.name, tag.from, tag.to));
+        }
+        // walk_se for the row of walk facing south east
+        let row_length = frames.len() / directions.len().max(1);
+        for (row, direction) in directions.iter().enumerate() {
+            animations.push(animation(
+                &format!("{}_{}", name, direction),
+                row * row_length,
+                (row + 1) * row_length - 1,
+            ));
+        }
+        // tiles are looked up by name, a tileset has no animation
+        if animations.is_empty() && !tileset {
+            animations.push(animation(&name, 0, frames.len() - 1));
+        }
+        let no_swaps = BTreeMap::new();
+        let swaps = config.map_or(&no_swaps, |config| &config.swaps);
+        let palette = match &sheet_options.palette {
+            Some(path) => {
+                let mut load = |path: &PathBuf| {
+                    palettes
+                        .entry(path.clone())
+                        .or_insert_with(|| match Palette::load(path) {
+                            Ok(palette) => Some(palette),
+                            Err(message) => {
+                                issues.push(Issue {
+                                    path: path.clone(),
+                                    message,
+                                });
+                                None
+                            }
+                        })
+                        .clone()
+                };
+                let base = load(path);
+                let swaps: Vec<_> = swaps
+                    .iter()
+                    .filter_map(|(name, path)| {
+                        Some((name.clone(), load(path)?))
+                    })
+                    .collect();
+                base.map(|base| SheetPalette { base, swaps })
+            }
+            None => {
+                if !swaps.is_empty() {
+                    issues.push(Issue {
+                        path: forge_dir.join(&dir),
+                        message: "palette swaps need a palette".into(),
+                    });
+                }
+                None
+            }
+        };
+        if let Some(palette) = &palette {
+            let colors = palette.base.colors.len();
+            for (name, swap) in &palette.swaps {
+                if swap.colors.len() != colors {
+                    issues.push(Issue {
+                        path: forge_dir.join(&dir),
+                        message: format!(
+                            "swap {} has {} colors, the palette has {}",
+                            name,
+                            swap.colors.len(),
+                            colors
+                        ),
+                    });
+                }
+            }
+            if sheet_options.indexed && colors > 256 {
+                issues.push(Issue {
+                    path: forge_dir.join(&dir),
+                    message: format!(
+                        "indexed pages hold 256 colors, the palette has {}",
+                        colors
+                    ),
+                });
+            }
+        } else if sheet_options.indexed && sheet_options.palette.is_none() {
+            issues.push(Issue {
+                path: forge_dir.join(&dir),
+                message: "indexed pages need a palette".into(),
+            });
+        }
+        sheets.push(SheetSource {
+            output_dir: assets_dir.join(&dir),
+            dir,
+            name,
+            frames,
+            options: sheet_options,
+            animations,
+            directions,
+            palette,
+            layered: document,
+        });
+    }
+    (sheets, issues)
+}
+
+// what a file in the input directory contributes
+enum Input {
+    Config(DirConfig),
+    Layered(String, Vec<FrameSource>, aseprite::Document),
+    // frame or tile, named once the config of its directory is known
+    Png((u32, u32)),
+}
+
+fn read_input(path: &Path) -> Result<Input, String> {
+    if path.file_name().is_some_and(|name| name == CONFIG_FILE) {
+        return DirConfig::load(path).map(Input::Config);
+    }
+    if path
+        .extension()
+        .is_some_and(|ext| ext == "ase" || ext == "aseprite")
+    {
+        let (name, frames, document) = layered_source(path)?;
+        return Ok(Input::Layered(name, frames, document));
+    }
+    if path.extension().is_none_or(|ext| ext != "png") {
+        return Err("not a png file".into());
+    }
+    // only the header, decoding happens when the sheet is built
+    let size = image::image_dimensions(path)
+        .map_err(|e| format!("cannot read png: {}", e))?;
+    Ok(Input::Png(size))
+}
+
+fn frame_source(
+    path: &Path,
+    size: (u32, u32),
+) -> Result<(String, FrameSource), String> {
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or("file name is not valid UTF-8")?;
+    // the last underscore separates the frame, walk_north_3 is walk_north
+    // and walk_ne_3 is walk facing north east
+    let Some((name, suffix)) = stem.rsplit_once('_') else {
+        return Err(format!("{} has no _<frame> suffix", stem));
+    };
+    if name.is_empty() {
+        return Err(format!("{} has no name before the frame number", stem));
+    }
+    let number = suffix
+        .parse()
+        .map_err(|_| format!("frame number {} is not a number", suffix))?;
+    let (name, direction) = match name.rsplit_once('_') {
+        Some((base, direction)) if !base.is_empty() => {
+            match DIRECTIONS.iter().find(|&&known| known == direction) {
+                Some(&direction) => (base, Some(direction)),
+                None => (name, None),
+            }
+        }
+        _ => (name, None),
+    };
+    Ok((
+        name.to_string(),
+        FrameSource {
+            path: path.to_path_buf(),
+            number,
+            size,
+            direction,
+            tile: None,
+        },
+    ))
+}
+
+// tiles/grass.png is tile grass of the tiles sheet, an optional _<number>
+// adds variants like water_0.png and water_1.png
+fn tile_source(
+    dir: &Path,
+    path: &Path,
+    size: (u32, u32),
+) -> Result<((PathBuf, String), FrameSource), String> {
+    let sheet = dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or("a tileset needs a directory of its own")?;
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or("file name is not valid UTF-8")?;
+    let (tile, number) = match stem.rsplit_once('_') {
+        Some((tile, suffix)) if !tile.is_empty() => match suffix.parse() {
+            Ok(number) => (tile, number),
+            Err(_) => (stem, 0),
+        },
+        _ => (stem, 0),
+    };
+    let parent = dir.parent().unwrap_or(Path::new(""));
+    Ok((
+        (parent.to_path_buf(), sheet.to_string()),
+        FrameSource {
+            path: path.to_path_buf(),
+            number,
+            size,
+            direction: None,
+            tile: Some(tile.to_string()),
+        },
+    ))
+}
+
+// every frame of the file is a frame of the sheet named after the file
+fn layered_source(
+    path: &Path,
+) -> Result<(String, Vec<FrameSource>, aseprite::Document), String> {
+    let name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or("file name is not valid UTF-8")?;
+    let data = fs::read(path).map_err(|e| format!("cannot read: {}", e))?;
+    let document = aseprite::read(&data)?;
+    if document.frames.is_empty() {
+        return Err("aseprite file has no frames".into());
+    }
+    let frames = (0..document.frames.len() as u32)
+        .map(|number| FrameSource {
+            path: path.to_path_buf(),
+            number,
+            size: (document.width, document.height),
+            direction: None,
+            tile: None,
+        })
+        .collect();
+    Ok((name.to_string(), frames, document))
+}
+
+fn check_frames(
+    frames: &[FrameSource],
+    frame_size: Option<(u32, u32)>,
+) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let first = &frames[0];
+    for frame in frames {
+        let message = match frame_size {
+            Some((w, h)) if frame.size != (w, h) => format!(
+                "frame is {}x{}, config expects {}x{}",
+                frame.size.0, frame.size.1, w, h
+            ),
+            None if frame.size != first.size => format!(
+                "frame is {}x{}, expected {}x{} like {}",
+                frame.size.0,
+                frame.size.1,
+                first.size.0,
+                first.size.1,
+                first.path.display()
+            ),
+            _ => continue,
+        };
+        issues.push(Issue {
+            path: frame.path.clone(),
+            message,
+        });
+    }
+
+    for pair in frames.windows(2) {
+        let (previous, frame) = (&pair[0], &pair[1]);
+        // differences, frame numbers may be as large as u32 allows
+        let gap = frame.number - previous.number;
+        let message = if gap == 0 {
+            format!("duplicates frame number {}", frame.number)
+        } else if gap == 2 {
+            format!("frame {} is missing before this one", frame.number - 1)
+        } else if gap > 2 {
+            format!(
+                "frames {} to {} are missing before this one",
+                previous.number + 1,
+                frame.number - 1
+            )
+        } else {
+            continue;
+        };
+        issues.push(Issue {
+            path: frame.path.clone(),
+            message,
+        });
+    }
+    issues
+}
@@ -0,0 +1,107 @@
+use image::{Rgba, RgbaImage};
+use serde::Deserialize;
+
+// pixels of slope around the edge of a sprite in generated normal maps
+const NORMAL_BEVEL: u32 = 4;
+
+// a page derived pixel by pixel from the frames, same layout as the sheet
+#[derive(Deserialize, Hash, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Companion {
+    // transparent pixels bordering the sprite, for selection effects
+    Outline,
+    Silhouette,
+    // bevelled from the alpha channel, for lighting
+    Normal,
+}
+impl Companion {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Outline => "outline",
+            Self::Silhouette => "silhouette",
+            Self::Normal => "normal",
+        }
+    }
+
+    pub fn render(self, image: &image::DynamicImage) -> RgbaImage {
+        let image = image.to_rgba8();
+        match self {
+            Self::Outline => outline(&image),
+            Self::Silhouette => silhouette(&image),
+            Self::Normal => normal_map(&image),
+        }
+    }
+}
+
+// trimmed frames have no room for the outline along their edges
+fn outline(image: &RgbaImage) -> RgbaImage {
+    let (w, h) = image.dimensions();
+    let opaque = |x: i64, y: i64| {
+        x >= 0
+            && y >= 0
+            && x < w as i64
+            && y < h as i64
+            && image.get_pixel(x as u32, y as u32)[3] > 0
+    };
+    RgbaImage::from_fn(w, h, |x, y| {
+        let (x, y) = (x as i64, y as i64);
+        let edge = !opaque(x, y)
+            && [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+                .into_iter()
+                .any(|(x, y)| opaque(x, y));
+        Rgba(if edge { [255; 4] } else { [0; 4] })
+    })
+}
+
+fn silhouette(image: &RgbaImage) -> RgbaImage {
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        match image.get_pixel(x, y)[3] {
+            0 => Rgba([0; 4]),
+            alpha => Rgba([255, 255, 255, alpha]),
+        }
+    })
+}
+
+// height is the distance to the nearest transparent pixel up to the bevel,
+// y points up like in OpenGL normal maps
+fn normal_map(image: &RgbaImage) -> RgbaImage {
+    let (w, h) = image.dimensions();
+    let at = |x: u32, y: u32| (y * w + x) as usize;
+    let mut distance = vec![0; (w * h) as usize];
+    for y in 0..h {
+        for x in 0..w {
+            if image.get_pixel(x, y)[3] > 0 {
+                let left = if x > 0 { distance[at(x - 1, y)] } else { 0 };
+                let up = if y > 0 { distance[at(x, y - 1)] } else { 0 };
+                distance[at(x, y)] = 1 + left.min(up);
+            }
+        }
+    }
+    for y in (0..h).rev() {
+        for x in (0..w).rev() {
+            if distance[at(x, y)] > 0 {
+                let right = if x + 1 < w { distance[at(x + 1, y)] } else { 0 };
+                let down = if y + 1 < h { distance[at(x, y + 1)] } else { 0 };
+                distance[at(x, y)] =
+                    distance[at(x, y)].min(1 + right.min(down));
+            }
+        }
+    }
+    let height = |x: i64, y: i64| {
+        if x < 0 || y < 0 || x >= w as i64 || y >= h as i64 {
+            return 0.0;
+        }
+        distance[at(x as u32, y as u32)].min(NORMAL_BEVEL) as f32
+    };
+    RgbaImage::from_fn(w, h, |x, y| {
+        let alpha = image.get_pixel(x, y)[3];
+        if alpha == 0 {
+            return Rgba([128, 128, 255, 0]);
+        }
+        let (x, y) = (x as i64, y as i64);
+        let dx = (height(x + 1, y) - height(x - 1, y)) / 2.0;
+        let dy = (height(x, y + 1) - height(x, y - 1)) / 2.0;
+        let length = (dx * dx + dy * dy + 1.0).sqrt();
+        let encode = |n: f32| ((n / length + 1.0) / 2.0 * 255.0).round() as u8;
+        Rgba([encode(-dx), encode(dy), encode(1.0), alpha])
+    })
+}
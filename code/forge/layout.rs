@@ -0,0 +1,176 @@
+use crate::{ForgeError, PackOptions};
+use image::{GenericImageView, ImageBuffer};
+
+#[derive(Default)]
+pub struct Packing {
+    pub pages: Vec<(u32, u32)>,
+    // page, x, y for every frame in input order
+    pub placements: Vec<(usize, u32, u32)>,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+fn check_fits(
+    sizes: &[(u32, u32)],
+    options: &PackOptions,
+) -> Result<(), ForgeError> {
+    for &(w, h) in sizes {
+        if w > options.max_size || h > options.max_size {
+            return Err(ForgeError::Pack(format!(
+                "frame {}x{} exceeds max texture size {}",
+                w, h, options.max_size
+            )));
+        }
+    }
+    Ok(())
+}
+
+// near-square grid of equal cells, the cell fits the largest frame
+// row_length fixes the columns, a page then only holds whole rows
+pub fn pack_grid(
+    sizes: &[(u32, u32)],
+    options: &PackOptions,
+    row_length: Option<u32>,
+) -> Result<Packing, ForgeError> {
+    check_fits(sizes, options)?;
+    if sizes.is_empty() {
+        return Ok(Packing::default());
+    }
+    let cell_w = sizes.iter().map(|&(w, _)| w).max().unwrap_or(0);
+    let cell_h = sizes.iter().map(|&(_, h)| h).max().unwrap_or(0);
+    let padding = options.padding;
+    let mut fit_columns = (options.max_size + padding) / (cell_w + padding);
+    let fit_rows = (options.max_size + padding) / (cell_h + padding);
+    if let Some(row_length) = row_length {
+        if row_length > fit_columns {
+            return Err(ForgeError::Pack(format!(
+                "a row of {} frames is wider than max texture size {}",
+                row_length, options.max_size
+            )));
+        }
+        fit_columns = row_length;
+    }
+    let per_page = (fit_columns * fit_rows) as usize;
+
+    let mut packing = Packing {
+        pages: Vec::new(),
+        placements: Vec::new(),
+        columns: 0,
+        rows: 0,
+    };
+    for chunk in sizes.chunks(per_page.max(1)) {
+        let count = chunk.len() as u32;
+        let mut columns =
+            row_length.unwrap_or_else(|| (count as f32).sqrt().ceil() as u32);
+        let mut rows = count.div_ceil(columns);
+        if columns > fit_columns || rows > fit_rows {
+            columns = fit_columns;
+            rows = count.div_ceil(columns);
+        }
+        if packing.pages.is_empty() {
+            packing.columns = columns;
+            packing.rows = rows;
+        }
+        let page = packing.pages.len();
+        packing.pages.push((
+            columns * cell_w + (columns - 1) * padding,
+            rows * cell_h + (rows - 1) * padding,
+        ));
+        for i in 0..count {
+            packing.placements.push((
+                page,
+                (i % columns) * (cell_w + padding),
+                (i / columns) * (cell_h + padding),
+            ));
+        }
+    }
+    Ok(packing)
+}
+
+// tallest frames first, rows of frames wrapped at a near-square width
+pub fn pack_shelf(
+    sizes: &[(u32, u32)],
+    options: &PackOptions,
+) -> Result<Packing, ForgeError> {
+    check_fits(sizes, options)?;
+    let padding = options.padding;
+    let area: u64 = sizes
+        .iter()
+        .map(|&(w, h)| (w + padding) as u64 * (h + padding) as u64)
+        .sum();
+    let widest = sizes.iter().map(|&(w, _)| w).max().unwrap_or(0);
+    let target_w = ((area as f64).sqrt().ceil() as u32)
+        .max(widest)
+        .min(options.max_size);
+
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(sizes[i].1));
+
+    let mut packing = Packing {
+        pages: Vec::new(),
+        placements: vec![(0, 0, 0); sizes.len()],
+        columns: 0,
+        rows: 0,
+    };
+    let (mut x, mut y, mut shelf_h) = (0, 0, 0);
+    let (mut page_w, mut page_h) = (0, 0);
+    for i in order {
+        let (w, h) = sizes[i];
+        if x > 0 && x + w > target_w {
+            x = 0;
+            y += shelf_h + padding;
+            shelf_h = 0;
+        }
+        if y > 0 && y + h > options.max_size {
+            packing.pages.push((page_w, page_h));
+            (x, y, shelf_h, page_w, page_h) = (0, 0, 0, 0, 0);
+        }
+        packing.placements[i] = (packing.pages.len(), x, y);
+        page_w = page_w.max(x + w);
+        page_h = page_h.max(y + h);
+        shelf_h = shelf_h.max(h);
+        x += w + padding;
+    }
+    if !sizes.is_empty() {
+        packing.pages.push((page_w, page_h));
+    }
+    Ok(packing)
+}
+
+// packed frames onto their pages, edges repeated outwards by extrude
+pub fn compose_pages<'a, I>(
+    packing: &Packing,
+    images: impl IntoIterator<Item = &'a I>,
+    extrude: u32,
+) -> Vec<image::RgbaImage>
+where
+    I: GenericImageView<Pixel = image::Rgba<u8>> + 'a,
+{
+    let mut pages: Vec<_> = packing
+        .pages
+        .iter()
+        .map(|&(w, h)| ImageBuffer::new(w, h))
+        .collect();
+    for (image, &(page, x, y)) in images.into_iter().zip(&packing.placements) {
+        if extrude == 0 {
+            image::imageops::replace(
+                &mut pages[page],
+                image,
+                x as i64,
+                y as i64,
+            );
+            continue;
+        }
+        let (w, h) = image.dimensions();
+        for sy in 0..h + 2 * extrude {
+            for sx in 0..w + 2 * extrude {
+                let pixel = image.get_pixel(
+                    sx.saturating_sub(extrude).min(w - 1),
+                    sy.saturating_sub(extrude).min(h - 1),
+                );
+                pages[page].put_pixel(x + sx, y + sy, pixel);
+            }
+        }
+    }
+    pages
+}
@@ -0,0 +1,314 @@
+// packs animation frames into spritesheets with their metadata, the forge
+// binary is a thin command line over this
+use rayon::prelude::*;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub mod aseprite;
+mod build;
+mod codegen;
+mod collect;
+mod companion;
+mod layout;
+mod manifest;
+mod options;
+mod palette;
+#[path = "../sheet.rs"]
+pub mod sheet;
+
+pub use build::{pack, PackedSheet};
+pub use codegen::rust_manifest;
+pub use companion::Companion;
+pub use options::{PackOptions, PackOverrides};
+pub use palette::Palette;
+
+use build::SharedFrames;
+use collect::SheetSource;
+use manifest::Manifest;
+
+pub const MAX_TEXTURE_SIZE: u32 = 4096;
+pub const MANIFEST_FILE: &str = ".manifest.ron";
+pub const CONFIG_FILE: &str = "forge.ron";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ForgeError {
+    #[error("input directory {} does not exist", .0.display())]
+    MissingInput(PathBuf),
+    #[error("{} problems in input files, nothing was generated", .0.len())]
+    Invalid(Vec<Issue>),
+    #[error(
+        "{} frames use colors outside their palette, nothing was generated",
+        .0.len()
+    )]
+    OffPalette(Vec<Issue>),
+    #[error("no sheet named {0}")]
+    UnknownSheet(String),
+    // frames that cannot be laid out within the options
+    #[error("{0}")]
+    Pack(String),
+    #[error("cannot flatten aseprite frame: {0}")]
+    Layered(String),
+    #[error("cannot generate the rust manifest: {0}")]
+    Codegen(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
+    #[error(transparent)]
+    Ron(#[from] ron::Error),
+}
+
+// a problem with one input file, reported before anything is written
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub path: PathBuf,
+    pub message: String,
+}
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+// progress of a run, printing it is up to the caller
+pub enum Event<'a> {
+    // kind is spritesheet, companion, palette lookup, metadata or rust
+    // manifest
+    Generated {
+        kind: &'static str,
+        path: &'a Path,
+    },
+    // where a frame of a generated sheet ended up
+    Placed {
+        frame: &'a str,
+        page: &'a str,
+        x: u32,
+        y: u32,
+    },
+    UpToDate {
+        meta: &'a Path,
+    },
+    WouldGenerate {
+        meta: &'a Path,
+        frames: Vec<&'a Path>,
+    },
+    // output of a sheet without inputs or a page a sheet no longer has
+    Removed {
+        path: &'a Path,
+    },
+}
+
+pub struct Forge {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    // the config of an input directory overrides these for it
+    pub pack: PackOptions,
+    // override the directory configs in turn
+    pub overrides: PackOverrides,
+    // report what would be generated, write nothing
+    pub dry_run: bool,
+    // sheet names or dir/name keys, empty builds everything
+    pub only: Vec<String>,
+    // rust source listing every sheet of the output, see rust_manifest
+    pub rust_manifest: Option<PathBuf>,
+}
+impl Forge {
+    pub fn new(input: impl Into<PathBuf>, output: impl Into<PathBuf>) -> Self {
+        Self {
+            input: input.into(),
+            output: output.into(),
+            pack: PackOptions::default(),
+            overrides: PackOverrides::default(),
+            dry_run: false,
+            only: Vec::new(),
+            rust_manifest: None,
+        }
+    }
+
+    fn selects(&self, source: &SheetSource) -> bool {
+        self.only.is_empty()
+            || self
+                .only
+                .iter()
+                .any(|name| *name == source.name || *name == source.key())
+    }
+
+    // force rebuilds sheets that the manifest has as up to date
+    pub fn run(
+        &self,
+        force: bool,
+        on_event: impl FnMut(Event) + Send,
+    ) -> Result<(), ForgeError> {
+        if !self.input.is_dir() {
+            return Err(ForgeError::MissingInput(self.input.clone()));
+        }
+        let (mut sheets, issues) = collect::collect_sheets(
+            &self.input,
+            &self.output,
+            &self.pack,
+            &self.overrides,
+        );
+        if !issues.is_empty() {
+            return Err(ForgeError::Invalid(issues));
+        }
+        // sheets of one directory next to each other for build_units
+        sheets.sort_by(|a, b| (&a.dir, &a.name).cmp(&(&b.dir, &b.name)));
+        for name in &self.only {
+            if !sheets
+                .iter()
+                .any(|source| *name == source.name || *name == source.key())
+            {
+                return Err(ForgeError::UnknownSheet(name.clone()));
+            }
+        }
+
+        let mut hashes = sheets
+            .par_iter()
+            .map(manifest::sheet_hash)
+            .collect::<Result<Vec<_>, _>>()?;
+        let units = manifest::build_units(&sheets, &mut hashes);
+
+        let manifest_path = self.input.join(MANIFEST_FILE);
+        let previous = Manifest::load(&manifest_path);
+        // None for units left out by the sheet filter
+        let stale: Vec<Option<bool>> = units
+            .iter()
+            .map(|unit| {
+                let sources = &sheets[unit.clone()];
+                sources.iter().any(|source| self.selects(source)).then(|| {
+                    force
+                        || sources.iter().zip(&hashes[unit.clone()]).any(
+                            |(source, hash)| {
+                                previous.sheets.get(&source.key()) != Some(hash)
+                                    || !manifest::sheet_outputs(
+                                        &source.meta_path(),
+                                    )
+                                    .is_some_and(|files| {
+                                        files.iter().all(|file| file.exists())
+                                    })
+                            },
+                        )
+                })
+            })
+            .collect();
+
+        // palettes need decoded frames, only sheets about to be built pay,
+        // every frame is dropped again right after its check
+        let checks: Vec<_> = units
+            .iter()
+            .zip(&stale)
+            .filter(|(_, stale)| **stale == Some(true))
+            .flat_map(|(unit, _)| &sheets[unit.clone()])
+            .filter_map(|source| Some((source, source.palette.as_ref()?)))
+            .flat_map(|(source, palette)| {
+                source
+                    .frames
+                    .iter()
+                    .map(move |frame| (source, frame, palette))
+            })
+            .collect();
+        let issues: Vec<Issue> = checks
+            .into_par_iter()
+            .map(|(source, frame, palette)| {
+                let frame = build::decode_frame(source, frame)?;
+                Ok(palette::check_palette(&frame, &palette.base))
+            })
+            .collect::<Result<Vec<_>, ForgeError>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+        if !issues.is_empty() {
+            return Err(ForgeError::OffPalette(issues));
+        }
+
+        // units share nothing, so they are built on all cores at once
+        let on_event = Mutex::new(on_event);
+        let entries = units
+            .into_par_iter()
+            .zip(stale)
+            .map(|(unit, stale)| {
+                let sources = &sheets[unit.clone()];
+                let hashes = &hashes[unit];
+                let Some(stale) = stale else {
+                    return Ok(sources
+                        .iter()
+                        .filter_map(|source| {
+                            let key = source.key();
+                            let hash = *previous.sheets.get(&key)?;
+                            Some((key, hash))
+                        })
+                        .collect());
+                };
+                let mut shared = SharedFrames::default();
+                let mut entries = Vec::new();
+                for (source, &hash) in sources.iter().zip(hashes) {
+                    let meta_path = source.meta_path();
+                    if !stale {
+                        emit(&on_event, Event::UpToDate { meta: &meta_path });
+                    } else if self.dry_run {
+                        let frames = source
+                            .frames
+                            .iter()
+                            .map(|frame| frame.path.as_path())
+                            .collect();
+                        emit(
+                            &on_event,
+                            Event::WouldGenerate {
+                                meta: &meta_path,
+                                frames,
+                            },
+                        );
+                    } else {
+                        build::build_sheet(source, &mut shared, &on_event)?;
+                    }
+                    entries.push((source.key(), hash));
+                }
+                Ok(entries)
+            })
+            .collect::<Result<Vec<Vec<_>>, ForgeError>>()?;
+        let manifest = Manifest {
+            sheets: entries.into_iter().flatten().collect(),
+        };
+        if self.dry_run {
+            return Ok(());
+        }
+        // sheets whose inputs are all gone
+        for key in previous.sheets.keys() {
+            if manifest.sheets.contains_key(key) {
+                continue;
+            }
+            let meta_path =
+                self.output
+                    .join(format!("{}.{}", key, sheet::SHEET_EXTENSION));
+            for path in manifest::sheet_outputs(&meta_path).unwrap_or_default()
+            {
+                if path.exists() {
+                    fs::remove_file(&path)?;
+                    emit(&on_event, Event::Removed { path: &path });
+                }
+            }
+        }
+        manifest.save(&manifest_path)?;
+        if let Some(path) = &self.rust_manifest {
+            let source = rust_manifest(&self.output)?;
+            // an unchanged file keeps cargo from rebuilding the game
+            if fs::read_to_string(path).ok().as_deref() != Some(&*source) {
+                fs::write(path, source)?;
+                emit(
+                    &on_event,
+                    Event::Generated {
+                        kind: "rust manifest",
+                        path,
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn emit(on_event: &Mutex<impl FnMut(Event)>, event: Event) {
+    (on_event.lock().unwrap())(event);
+}
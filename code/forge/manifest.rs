@@ -0,0 +1,111 @@
+use crate::collect::SheetSource;
+use crate::{sheet, ForgeError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+// input hash of every sheet from the previous run, keyed by dir/name
+#[derive(Serialize, Deserialize, Default)]
+pub struct Manifest {
+    pub sheets: BTreeMap<String, u64>,
+}
+impl Manifest {
+    // a missing or unreadable manifest just means everything is rebuilt
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|text| ron::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ForgeError> {
+        let pretty = ron::ser::PrettyConfig::default();
+        fs::write(path, ron::ser::to_string_pretty(self, pretty)?)?;
+        Ok(())
+    }
+}
+
+// the metadata of a sheet and every image it lists, None when the
+// metadata is missing or unreadable
+pub fn sheet_outputs(meta_path: &Path) -> Option<Vec<PathBuf>> {
+    let text = fs::read_to_string(meta_path).ok()?;
+    let meta: sheet::SheetMeta = ron::from_str(&text).ok()?;
+    let dir = meta_path.parent()?;
+    let mut files = vec![meta_path.to_path_buf()];
+    for page in &meta.pages {
+        files.push(dir.join(&page.image));
+        files.extend(page.companions.values().map(|image| dir.join(image)));
+    }
+    files.extend(meta.palette.iter().map(|palette| dir.join(&palette.image)));
+    Some(files)
+}
+
+// FNV-1a, unlike the std hasher it is stable between toolchains
+pub struct Fnv(u64);
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+impl Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// sheets that are always rebuilt together, one per sheet unless the
+// directory shares frames across sheets, those also share one hash
+pub fn build_units(
+    sheets: &[SheetSource],
+    hashes: &mut [u64],
+) -> Vec<Range<usize>> {
+    let mut units: Vec<Range<usize>> = Vec::new();
+    for (i, source) in sheets.iter().enumerate() {
+        match units.last_mut() {
+            Some(unit)
+                if source.options.dedupe_across
+                    && sheets[unit.start].dir == source.dir =>
+            {
+                unit.end = i + 1
+            }
+            _ => units.push(i..i + 1),
+        }
+    }
+    for unit in &units {
+        if unit.len() > 1 {
+            let mut hasher = Fnv::default();
+            hashes[unit.clone()].hash(&mut hasher);
+            let hash = hasher.finish();
+            hashes[unit.clone()].fill(hash);
+        }
+    }
+    units
+}
+
+// covers file names, file contents, pack options and animation settings
+pub fn sheet_hash(source: &SheetSource) -> Result<u64, ForgeError> {
+    let mut hasher = Fnv::default();
+    source.options.hash(&mut hasher);
+    source.animations.hash(&mut hasher);
+    source.palette.hash(&mut hasher);
+    let mut previous = None;
+    for frame in &source.frames {
+        frame.path.file_name().hash(&mut hasher);
+        // a layered file holds every frame of its sheet, read it once
+        if previous != Some(&frame.path) {
+            fs::read(&frame.path)?.hash(&mut hasher);
+        }
+        previous = Some(&frame.path);
+    }
+    Ok(hasher.finish())
+}
@@ -0,0 +1,172 @@
+use crate::{sheet, Companion, MAX_TEXTURE_SIZE};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+#[derive(Hash, Clone, Debug)]
+pub struct PackOptions {
+    pub layout: sheet::SheetLayout,
+    pub max_size: u32,
+    pub padding: u32,
+    // edge pixels repeated around every frame against texture bleeding
+    pub extrude: u32,
+    pub trim: bool,
+    // share identical frames with other sheets of the same directory
+    pub dedupe_across: bool,
+    // every opaque pixel must use one of these colors
+    pub palette: Option<PathBuf>,
+    // pages store palette indices, colors come from the lookup texture
+    pub indexed: bool,
+    // derived sheets written next to every page
+    pub companions: Vec<Companion>,
+}
+impl Default for PackOptions {
+    fn default() -> Self {
+        Self {
+            layout: sheet::SheetLayout::Grid,
+            max_size: MAX_TEXTURE_SIZE,
+            padding: 0,
+            extrude: 0,
+            trim: false,
+            dedupe_across: false,
+            palette: None,
+            indexed: false,
+            companions: Vec::new(),
+        }
+    }
+}
+
+// pack options set explicitly on the command line, unlike PackOptions they
+// win over every directory config
+#[derive(Clone, Default, Debug)]
+pub struct PackOverrides {
+    pub layout: Option<sheet::SheetLayout>,
+    pub max_size: Option<u32>,
+    pub padding: Option<u32>,
+    pub extrude: Option<u32>,
+    pub trim: Option<bool>,
+    pub dedupe_across: Option<bool>,
+    pub palette: Option<PathBuf>,
+    pub indexed: Option<bool>,
+    pub companions: Option<Vec<Companion>>,
+}
+impl PackOverrides {
+    pub fn apply(&self, options: &mut PackOptions) {
+        if let Some(layout) = self.layout {
+            options.layout = layout;
+        }
+        if let Some(max_size) = self.max_size {
+            options.max_size = max_size;
+        }
+        if let Some(padding) = self.padding {
+            options.padding = padding;
+        }
+        if let Some(extrude) = self.extrude {
+            options.extrude = extrude;
+        }
+        if let Some(trim) = self.trim {
+            options.trim = trim;
+        }
+        if let Some(dedupe_across) = self.dedupe_across {
+            options.dedupe_across = dedupe_across;
+        }
+        if let Some(palette) = &self.palette {
+            options.palette = Some(palette.clone());
+        }
+        if let Some(indexed) = self.indexed {
+            options.indexed = indexed;
+        }
+        if let Some(companions) = &self.companions {
+            options.companions = companions.clone();
+        }
+    }
+}
+
+// optional forge.ron in an input directory, subdirectories without their own
+// inherit it, fields that are set override PackOptions but not PackOverrides
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct DirConfig {
+    layout: Option<sheet::SheetLayout>,
+    max_size: Option<u32>,
+    padding: Option<u32>,
+    extrude: Option<u32>,
+    trim: Option<bool>,
+    dedupe_across: Option<bool>,
+    // relative to the directory of the config, like the swaps
+    palette: Option<PathBuf>,
+    indexed: Option<bool>,
+    // recolors of the palette by name, each with the same number of colors
+    pub swaps: BTreeMap<String, PathBuf>,
+    companions: Option<Vec<Companion>>,
+    // every frame must have exactly this size
+    pub frame_size: Option<(u32, u32)>,
+    // the pngs of the directory are named tiles of one sheet named after it
+    pub tileset: bool,
+    // keyed by sheet name, or tag name for aseprite files
+    pub animations: HashMap<String, AnimationConfig>,
+}
+impl DirConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read config: {}", e))?;
+        let mut config: Self = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(&text)
+            .map_err(|e| format!("cannot parse config: {}", e))?;
+        for (name, animation) in &config.animations {
+            if let Some(fps) =
+                animation.fps.filter(|fps| !(fps.is_finite() && *fps > 0.0))
+            {
+                return Err(format!(
+                    "animation {} has fps {}, it must be a number above 0",
+                    name, fps
+                ));
+            }
+        }
+        let dir = path.parent().unwrap_or(Path::new(""));
+        config.palette = config.palette.map(|palette| dir.join(palette));
+        for swap in config.swaps.values_mut() {
+            *swap = dir.join(&swap);
+        }
+        Ok(config)
+    }
+
+    pub fn apply(&self, options: &mut PackOptions) {
+        let overrides = PackOverrides {
+            layout: self.layout,
+            max_size: self.max_size,
+            padding: self.padding,
+            extrude: self.extrude,
+            trim: self.trim,
+            dedupe_across: self.dedupe_across,
+            palette: self.palette.clone(),
+            indexed: self.indexed,
+            companions: self.companions.clone(),
+        };
+        overrides.apply(options);
+    }
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct AnimationConfig {
+    pub fps: Option<f32>,
+    pub looping: bool,
+}
+impl Default for AnimationConfig {
+    fn default() -> Self {
+        Self {
+            fps: None,
+            looping: true,
+        }
+    }
+}
+impl Hash for AnimationConfig {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.fps.map(f32::to_bits).hash(state);
+        self.looping.hash(state);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::build::Frame;
+use crate::{ForgeError, Issue};
+use image::GenericImageView;
+use std::fs;
+use std::path::Path;
+
+// off-palette pixels listed per frame, the rest are only counted
+const MAX_REPORTED_PIXELS: usize = 8;
+
+// colors in order, the position is the index written by indexed pages
+#[derive(Hash, Default, Clone, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: Vec<[u8; 3]>,
+}
+impl Palette {
+    // a .hex file with one rrggbb per line, or an image of swatches
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if path.extension().is_some_and(|ext| ext == "hex") {
+            let text = fs::read_to_string(path)
+                .map_err(|e| format!("cannot read palette: {}", e))?;
+            let mut palette = Self::default();
+            for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                let hex = line.trim_start_matches('#');
+                let value = u32::from_str_radix(hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == 6)
+                    .ok_or_else(|| {
+                        format!("{} is not an rrggbb color", line)
+                    })?;
+                palette.add([
+                    (value >> 16) as u8,
+                    (value >> 8) as u8,
+                    value as u8,
+                ]);
+            }
+            return Ok(palette);
+        }
+        let image = image::open(path)
+            .map_err(|e| format!("cannot read palette: {}", e))?;
+        Ok(Self::extract(&image))
+    }
+
+    // distinct colors of the opaque pixels in reading order
+    pub fn extract(image: &image::DynamicImage) -> Self {
+        let mut palette = Self::default();
+        for (_, _, pixel) in image.pixels() {
+            if pixel[3] > 0 {
+                palette.add([pixel[0], pixel[1], pixel[2]]);
+            }
+        }
+        palette
+    }
+
+    pub fn add(&mut self, color: [u8; 3]) {
+        if !self.colors.contains(&color) {
+            self.colors.push(color);
+        }
+    }
+
+    pub fn index(&self, color: [u8; 3]) -> Option<usize> {
+        self.colors.iter().position(|&known| known == color)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ForgeError> {
+        if path.extension().is_some_and(|ext| ext == "hex") {
+            let text: String = self
+                .colors
+                .iter()
+                .map(|[r, g, b]| format!("{:02x}{:02x}{:02x}\n", r, g, b))
+                .collect();
+            fs::write(path, text)?;
+            return Ok(());
+        }
+        let mut image = image::RgbaImage::new(self.colors.len() as u32, 1);
+        for (x, &[r, g, b]) in self.colors.iter().enumerate() {
+            image.put_pixel(x as u32, 0, image::Rgba([r, g, b, 255]));
+        }
+        image.save(path)?;
+        Ok(())
+    }
+}
+
+#[derive(Hash)]
+pub struct SheetPalette {
+    pub base: Palette,
+    // lookup texture rows after the base palette
+    pub swaps: Vec<(String, Palette)>,
+}
+
+// opaque pixels whose color is missing from the palette
+pub fn check_palette(frame: &Frame, palette: &Palette) -> Option<Issue> {
+    let off: Vec<_> = frame
+        .image
+        .pixels()
+        .filter(|(_, _, pixel)| {
+            pixel[3] > 0
+                && palette.index([pixel[0], pixel[1], pixel[2]]).is_none()
+        })
+        .collect();
+    if off.is_empty() {
+        return None;
+    }
+    let mut message = format!("{} pixels are off the palette:", off.len());
+    for (x, y, pixel) in off.iter().take(MAX_REPORTED_PIXELS) {
+        message.push_str(&format!(
+            " {},{} #{:02x}{:02x}{:02x}",
+            x, y, pixel[0], pixel[1], pixel[2]
+        ));
+    }
+    if off.len() > MAX_REPORTED_PIXELS {
+        message.push_str(" ...");
+    }
+    // the source names the frame inside a layered file too
+    Some(Issue {
+        path: frame.path.with_file_name(&frame.source),
+        message,
+    })
+}
@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// shared between forge and the game, keep it free of bevy types
+pub const SHEET_EXTENSION: &str = "sheet.ron";
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default,
+)]
+pub enum SheetLayout {
+    #[default]
+    Grid,
+    Shelf,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SheetMeta {
+    pub layout: SheetLayout,
+    // pixels of repeated edge around every frame rect
+    #[serde(default)]
+    pub extrude: u32,
+    pub pages: Vec<PageMeta>,
+    pub frame_w: u32,
+    pub frame_h: u32,
+    pub frame_count: usize,
+    // grid of the first page, zero for shelf layout
+    pub columns: u32,
+    pub rows: u32,
+    pub frames: Vec<FrameMeta>,
+    // facings of a character, each a grid row and an animation like walk_se
+    #[serde(default)]
+    pub directions: Vec<DirectionMeta>,
+    // set when pages hold palette indices in red instead of colors
+    #[serde(default)]
+    pub palette: Option<PaletteMeta>,
+    // tile names of a tileset, their variants are consecutive frames
+    #[serde(default)]
+    pub tiles: Vec<TileMeta>,
+    pub animations: Vec<AnimationMeta>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PageMeta {
+    pub image: String,
+    pub w: u32,
+    pub h: u32,
+    // derived images with the same layout, keyed by kind like "outline"
+    #[serde(default)]
+    pub companions: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DirectionMeta {
+    pub name: String,
+    // grid row counted across pages, its frames are its animation's range
+    pub row: u32,
+}
+
+// lookup texture, row 0 is the palette and row i + 1 recolors it as swaps[i]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PaletteMeta {
+    pub image: String,
+    pub colors: u32,
+    pub swaps: Vec<String>,
+}
+
+// grass for grass.png, water with 2 variants for water_0.png and water_1.png
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TileMeta {
+    pub name: String,
+    pub first: usize,
+    pub variants: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FrameMeta {
+    pub source: String,
+    pub page: usize,
+    pub rect: FrameRect,
+    #[serde(default)]
+    pub trim: Option<TrimMeta>,
+    // earlier frame with identical pixels, rect and page are shared
+    #[serde(default)]
+    pub duplicate_of: Option<usize>,
+}
+
+// where a trimmed frame sat inside its original canvas
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrimMeta {
+    pub x: u32,
+    pub y: u32,
+    pub source_w: u32,
+    pub source_h: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameRect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnimationMeta {
+    pub name: String,
+    pub first: usize,
+    pub last: usize,
+    // None leaves the playback speed to the game
+    #[serde(default)]
+    pub fps: Option<f32>,
+    #[serde(default = "looping_default")]
+    pub looping: bool,
+}
+
+fn looping_default() -> bool {
+    true
+}
@@ -0,0 +1,301 @@
+use crate::*;
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    math::URect,
+    render::texture::{ImageLoaderSettings, ImageSampler},
+    sprite::Anchor,
+    utils::HashMap,
+};
+use std::ops::Range;
+use std::path::PathBuf;
+
+pub struct SpritesheetPlugin;
+impl Plugin for SpritesheetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Spritesheet>()
+            .init_asset_loader::<SpritesheetLoader>()
+            .add_systems(Update, sync_sheet_sprites);
+    }
+}
+
+#[derive(Asset, TypePath, Debug)]
+pub struct Spritesheet {
+    pub pages: Vec<SheetPage>,
+    pub frames: Vec<SheetFrame>,
+    pub animations: HashMap<String, AnimationRange>,
+    // grid row of every facing, its animation is named like walk_se
+    pub directions: HashMap<String, u32>,
+    pub palette: Option<SheetPalette>,
+    // frames of every tile of a tileset, one per variant
+    pub tiles: HashMap<String, Range<usize>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SheetFrame {
+    pub page: usize,
+    // index within the page layout
+    pub index: usize,
+    // keeps the untrimmed center in place, None for untrimmed frames
+    pub anchor: Option<Vec2>,
+}
+
+#[derive(Debug)]
+pub struct SheetPage {
+    pub image: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    // outline, silhouette or normal images sharing the layout
+    pub companions: HashMap<String, Handle<Image>>,
+}
+
+// pages hold palette indices and this is their lookup, nothing in the game
+// samples it yet, a palette swap material still has to be written
+#[derive(Debug)]
+pub struct SheetPalette {
+    // row 0 is the palette, row i + 1 is swaps[i]
+    pub lookup: Handle<Image>,
+    pub colors: u32,
+    pub swaps: Vec<String>,
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct AnimationRange {
+    pub first: usize,
+    pub last: usize,
+    pub fps: Option<f32>,
+    pub looping: bool,
+}
+
+// drives texture and TextureAtlas, index is a frame of the whole sheet
+#[derive(Component)]
+pub struct SheetSprite {
+    pub sheet: Handle<Spritesheet>,
+    pub index: usize,
+}
+
+// optional, starts the named animation instead of SheetSprite index
+#[derive(Component)]
+pub struct SheetAnimation(pub String);
+
+#[derive(Default)]
+pub struct SpritesheetLoader;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SpritesheetLoaderError {
+    #[error("could not read spritesheet metadata: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse spritesheet metadata: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("frame refers to missing page {0}")]
+    MissingPage(usize),
+}
+
+impl AssetLoader for SpritesheetLoader {
+    type Asset = Spritesheet;
+    type Settings = ();
+    type Error = SpritesheetLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Spritesheet, SpritesheetLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let meta: sheet::SheetMeta = ron::de::from_bytes(&bytes)?;
+
+        let dir = load_context.path().parent().map(|dir| dir.to_path_buf());
+        let mut layouts: Vec<_> = meta
+            .pages
+            .iter()
+            .map(|page| {
+                TextureAtlasLayout::new_empty(UVec2::new(page.w, page.h))
+            })
+            .collect();
+        let mut frames = Vec::with_capacity(meta.frames.len());
+        for frame in &meta.frames {
+            let Some(layout) = layouts.get_mut(frame.page) else {
+                return Err(SpritesheetLoaderError::MissingPage(frame.page));
+            };
+            let duplicate = frame
+                .duplicate_of
+                .and_then(|i| frames.get(i))
+                .map(|original: &SheetFrame| original.index);
+            let index = duplicate.unwrap_or_else(|| {
+                layout.add_texture(URect::new(
+                    frame.rect.x,
+                    frame.rect.y,
+                    frame.rect.x + frame.rect.w,
+                    frame.rect.y + frame.rect.h,
+                ))
+            });
+            frames.push(SheetFrame {
+                page: frame.page,
+                index,
+                anchor: frame.trim.map(|trim| trim_anchor(&frame.rect, &trim)),
+            });
+        }
+
+        let mut pages = Vec::with_capacity(layouts.len());
+        for (i, (page, layout)) in meta.pages.iter().zip(layouts).enumerate() {
+            let path = |image: &String| match &dir {
+                Some(dir) => dir.join(image),
+                None => image.into(),
+            };
+            let companions = page
+                .companions
+                .iter()
+                .map(|(kind, image)| {
+                    let handle = match kind.as_str() {
+                        "normal" => load_data_image(load_context, path(image)),
+                        _ => load_context.load(path(image)),
+                    };
+                    (kind.clone(), handle)
+                })
+                .collect();
+            let image = match meta.palette {
+                Some(_) => load_data_image(load_context, path(&page.image)),
+                None => load_context.load(path(&page.image)),
+            };
+            pages.push(SheetPage {
+                image,
+                companions,
+                layout: load_context
+                    .add_labeled_asset(format!("layout{}", i), layout),
+            });
+        }
+
+        let palette = meta.palette.map(|palette| {
+            let lookup_path = match &dir {
+                Some(dir) => dir.join(&palette.image),
+                None => palette.image.into(),
+            };
+            SheetPalette {
+                lookup: load_data_image(load_context, lookup_path),
+                colors: palette.colors,
+                swaps: palette.swaps,
+            }
+        });
+
+        let directions = meta
+            .directions
+            .into_iter()
+            .map(|direction| (direction.name, direction.row))
+            .collect();
+
+        let animations = meta
+            .animations
+            .into_iter()
+            .map(|animation| {
+                let range = AnimationRange {
+                    first: animation.first,
+                    last: animation.last,
+                    fps: animation.fps,
+                    looping: animation.looping,
+                };
+                (animation.name, range)
+            })
+            .collect();
+
+        let tiles = meta
+            .tiles
+            .into_iter()
+            .map(|tile| (tile.name, tile.first..tile.first + tile.variants))
+            .collect();
+
+        Ok(Spritesheet {
+            pages,
+            frames,
+            animations,
+            directions,
+            palette,
+            tiles,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[sheet::SHEET_EXTENSION]
+    }
+}
+
+// palette indices, lookup rows and normals are numbers rather than colors,
+// so they are neither converted from srgb nor filtered
+fn load_data_image(
+    load_context: &mut LoadContext,
+    path: PathBuf,
+) -> Handle<Image> {
+    load_context
+        .loader()
+        .with_settings(|settings: &mut ImageLoaderSettings| {
+            settings.is_srgb = false;
+            settings.sampler = ImageSampler::nearest();
+        })
+        .load(path)
+}
+
+fn trim_anchor(rect: &sheet::FrameRect, trim: &sheet::TrimMeta) -> Vec2 {
+    let (w, h) = (rect.w as f32, rect.h as f32);
+    Vec2::new(
+        (trim.source_w as f32 / 2.0 - trim.x as f32 - w / 2.0) / w,
+        (trim.y as f32 + h / 2.0 - trim.source_h as f32 / 2.0) / h,
+    )
+}
+
+#[allow(clippy::type_complexity)]
+fn sync_sheet_sprites(
+    mut commands: Commands,
+    spritesheets: Res<Assets<Spritesheet>>,
+    mut query: Query<(
+        Entity,
+        &mut SheetSprite,
+        &mut Handle<Image>,
+        &mut Sprite,
+        Option<&mut TextureAtlas>,
+    )>,
+    animations: Query<&SheetAnimation, Without<AnimationRange>>,
+) {
+    for (entity, mut sheet_sprite, mut texture, mut sprite, atlas) in &mut query
+    {
+        if atlas.is_some() && !sheet_sprite.is_changed() {
+            continue;
+        }
+        let Some(spritesheet) = spritesheets.get(&sheet_sprite.sheet) else {
+            continue;
+        };
+        if let Ok(SheetAnimation(name)) = animations.get(entity) {
+            match spritesheet.animations.get(name) {
+                Some(range) => {
+                    sheet_sprite.index = range.first;
+                    commands.entity(entity).insert(*range);
+                }
+                None => warn!("spritesheet has no animation named {}", name),
+            }
+        }
+        let Some(frame) = spritesheet.frames.get(sheet_sprite.index) else {
+            warn!("spritesheet has no frame {}", sheet_sprite.index);
+            continue;
+        };
+        let index = frame.index;
+        if let Some(anchor) = frame.anchor {
+            sprite.anchor = Anchor::Custom(anchor);
+        }
+        let page = &spritesheet.pages[frame.page];
+        if *texture != page.image {
+            *texture = page.image.clone();
+        }
+        match atlas {
+            Some(mut atlas) => {
+                if atlas.layout != page.layout {
+                    atlas.layout = page.layout.clone();
+                }
+                atlas.index = index;
+            }
+            None => {
+                commands.entity(entity).insert(TextureAtlas {
+                    layout: page.layout.clone(),
+                    index,
+                });
+            }
+        }
+    }
+}
@@ -1,10 +1,15 @@
 use bevy::prelude::*;
 use bevy::window::*;
 
+// rewritten by forge --rust-manifest
+#[rustfmt::skip]
+pub mod assets;
 pub mod camera;
 pub mod dev;
 pub mod gamepad;
 pub mod motion;
+pub mod sheet;
+pub mod spritesheet;
 pub mod tilemap;
 pub mod time;
 
@@ -33,6 +38,7 @@ fn main() {
         camera::CameraPlugin,
         gamepad::GamepadPlugin,
         motion::MotionPlugin,
+        spritesheet::SpritesheetPlugin,
         dev::DevPlugin,
         tilemap::TilemapPlugin,
     ));
@@ -3,8 +3,6 @@ use rand::Rng;
 
 const TILE_SIZE_X: f32 = 16.0;
 const TILE_SIZE_Y: f32 = 8.0;
-const TILE_COUNT_X: u32 = 4;
-const TILE_COUNT_Y: u32 = 2;
 const MAP_WIDTH: u32 = 50;
 const MAP_HEIGHT: u32 = 30;
 
@@ -16,88 +14,156 @@ pub struct Tilemap {
     pub tiles: Vec<Vec<TileType>>,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub enum TileType {
-    Stone,
-    Grass,
-    Water,
-    Dirt,
+// name of a tile in the tileset, grass for forge/tiles/grass.png
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TileType(pub String);
+
+// extent of the Tilemap in tiles, kept up to date with it
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct MapBounds {
+    pub width: usize,
+    pub height: usize,
 }
 
-impl TileType {
-    fn to_index(&self) -> usize {
-        match self {
-            TileType::Stone => 0,
-            TileType::Grass => 1,
-            TileType::Water => 2,
-            TileType::Dirt => 3,
+impl MapBounds {
+    // closest point to center from which a view of half_size shows only the
+    // map, in map coordinates the iso diamond is a plain rectangle
+    pub fn clamp_view(&self, center: Vec2, half_size: Vec2) -> Vec2 {
+        if self.width == 0 || self.height == 0 {
+            return center;
         }
+        let tile = to_map(center);
+        let margin = half_size.x / TILE_SIZE_X + half_size.y / TILE_SIZE_Y;
+        // tiles cover half a tile around their centers, an axis the view
+        // does not fit in is left free so the camera keeps following
+        let clamp = |value: f32, size: usize| {
+            let (min, max) = (margin - 0.5, size as f32 - 0.5 - margin);
+            if min > max {
+                value
+            } else {
+                value.clamp(min, max)
+            }
+        };
+        let clamped =
+            Vec2::new(clamp(tile.x, self.width), clamp(tile.y, self.height));
+        to_world(clamped)
     }
+}
 
-    fn random() -> Self {
-        let mut rng = rand::thread_rng();
-        match rng.gen_range(0..4) {
-            0 => TileType::Stone,
-            1 => TileType::Grass,
-            2 => TileType::Water,
-            _ => TileType::Dirt,
-        }
-    }
+// map coordinates of the tiles, x along a row and y down the rows
+fn to_world(tile: Vec2) -> Vec2 {
+    Vec2::new(
+        (tile.x - tile.y) * TILE_SIZE_X / 2.0,
+        -(tile.x + tile.y) * TILE_SIZE_Y / 2.0,
+    )
 }
 
+fn to_map(world: Vec2) -> Vec2 {
+    let across = world.x / (TILE_SIZE_X / 2.0);
+    let down = -world.y / (TILE_SIZE_Y / 2.0);
+    Vec2::new((down + across) / 2.0, (down - across) / 2.0)
+}
+
+#[derive(Resource)]
+pub struct Tileset(pub Handle<spritesheet::Spritesheet>);
+
 pub struct TilemapPlugin;
 
 impl Plugin for TilemapPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Tilemap>()
-            .add_systems(Startup, setup_tilemap)
-            .add_systems(Startup, spawn_tiles.after(setup_tilemap));
+            .init_resource::<MapBounds>()
+            .add_systems(Startup, load_tileset)
+            .add_systems(
+                Update,
+                (
+                    setup_tilemap,
+                    (spawn_tiles, update_bounds)
+                        .run_if(resource_changed::<Tilemap>),
+                )
+                    .chain(),
+            );
     }
 }
 
-fn setup_tilemap(mut tilemap: ResMut<Tilemap>) {
+fn load_tileset(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(Tileset(asset_server.load(assets::tiles::PATH)));
+}
+
+// waits for the tileset, its tile names are the tile types
+fn setup_tilemap(
+    mut tilemap: ResMut<Tilemap>,
+    tileset: Res<Tileset>,
+    spritesheets: Res<Assets<spritesheet::Spritesheet>>,
+) {
+    if !tilemap.tiles.is_empty() {
+        return;
+    }
+    let Some(sheet) = spritesheets.get(&tileset.0) else {
+        return;
+    };
+    if sheet.tiles.is_empty() {
+        warn!("{} has no tiles", assets::tiles::PATH);
+        return;
+    }
+    // sorted so a seeded rng would always build the same map
+    let mut names: Vec<_> = sheet.tiles.keys().collect();
+    names.sort();
+    let mut rng = rand::thread_rng();
     tilemap.tiles = (0..MAP_HEIGHT)
-        .map(|_| (0..MAP_WIDTH).map(|_| TileType::random()).collect())
+        .map(|_| {
+            (0..MAP_WIDTH)
+                .map(|_| TileType(names[rng.gen_range(0..names.len())].clone()))
+                .collect()
+        })
         .collect();
 }
 
+fn update_bounds(tilemap: Res<Tilemap>, mut bounds: ResMut<MapBounds>) {
+    *bounds = MapBounds {
+        width: tilemap.tiles.first().map_or(0, Vec::len),
+        height: tilemap.tiles.len(),
+    };
+}
+
 fn spawn_tiles(
     mut commands: Commands,
     tilemap: Res<Tilemap>,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+    tileset: Res<Tileset>,
+    spritesheets: Res<Assets<spritesheet::Spritesheet>>,
+    tiles: Query<Entity, With<Tile>>,
 ) {
-    let texture_handle = asset_server.load("tiles.png");
-    let texture_atlas = TextureAtlasLayout::from_grid(
-        UVec2::new(TILE_SIZE_X as u32, TILE_SIZE_Y as u32),
-        TILE_COUNT_X,
-        TILE_COUNT_Y,
-        None,
-        None,
-    );
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
+    let Some(sheet) = spritesheets.get(&tileset.0) else {
+        return;
+    };
+    for entity in &tiles {
+        commands.entity(entity).despawn();
+    }
+    let mut rng = rand::thread_rng();
 
     for (y, row) in tilemap.tiles.iter().enumerate() {
         for (x, tile_type) in row.iter().enumerate() {
+            let Some(variants) = sheet.tiles.get(&tile_type.0) else {
+                warn!("tileset has no tile named {}", tile_type.0);
+                continue;
+            };
+            if variants.is_empty() {
+                warn!("tile {} has no variants", tile_type.0);
+                continue;
+            }
             commands.spawn((
                 SpriteBundle {
-                    texture: texture_handle.clone(),
                     transform: Transform {
-                        translation: Vec3::new(
-                            (x as f32 - y as f32) * TILE_SIZE_X / 2.0,
-                            -(x as f32 + y as f32) * TILE_SIZE_Y / 2.0,
-                            50.0,
-                        ),
+                        translation: to_world(Vec2::new(x as f32, y as f32))
+                            .extend(50.0),
                         scale: Vec3::splat(1.0),
                         ..default()
                     },
                     ..default()
                 },
-                TextureAtlas {
-                    layout: texture_atlas_handle.clone(),
-                    index: tile_type.to_index() % TILE_COUNT_X as usize
-                        + (tile_type.to_index() / TILE_COUNT_X as usize)
-                            * TILE_COUNT_X as usize,
+                spritesheet::SheetSprite {
+                    sheet: tileset.0.clone(),
+                    index: rng.gen_range(variants.clone()),
                 },
                 Tile,
             ));
@@ -1,71 +1,288 @@
-use image::{GenericImageView, ImageBuffer};
-use std::collections::HashMap;
-use std::fs;
+use forge::{Companion, Event, Forge, ForgeError, Palette, MANIFEST_FILE};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
 use walkdir::WalkDir;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let forge_dir = Path::new("forge");
-    let assets_dir = Path::new("assets");
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
 
-    for entry in WalkDir::new(forge_dir)
-        .min_depth(1)
-        .max_depth(1)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        if entry.file_type().is_dir() {
-            let relative_path = entry.path().strip_prefix(forge_dir)?;
-            let output_dir = assets_dir.join(relative_path);
-            fs::create_dir_all(&output_dir)?;
+const USAGE: &str = "\
+Usage: forge [options] [sheet...]
+
+Packs <input>/<dir>/<name>_<frame>.png into <output>/<dir>/<name>.png
+and its metadata. An aseprite file <input>/<dir>/<name>.aseprite becomes
+a sheet of its flattened visible layers with one animation per tag.
+A last name part of n, ne, e, se, s, sw, w or nw is a facing, as in
+<name>_se_<frame>.png, and packs a row per facing of a grid layout.
+Sheet names filter the build, either name or dir/name.
+A forge.ron in an input directory sets the pack options for it and its
+subdirectories and per-animation fps and looping, options given here win
+over it.
 
-            let mut spritesheets: HashMap<
-                String,
-                Vec<(PathBuf, image::DynamicImage)>,
-            > = HashMap::new();
+Options:
+    --input <dir>       frames to pack [default: forge]
+    --output <dir>      where sheets go [default: assets]
+    --layout <mode>     grid or shelf [default: grid]
+    --max-size <px>     largest page side before splitting [default: 4096]
+    --padding <px>      gap between frames [default: 0]
+    --extrude <px>      repeat frame edges outwards [default: 0]
+    --trim              crop frames to their opaque pixels
+    --dedupe-across     share identical frames between sheets of a directory
+    --palette <file>    reject colors missing from a .png or .hex palette
+    --indexed           write palette indices and a palette lookup texture
+    --companions <list> also write outline, silhouette or normal sheets,
+                        comma separated
+    --rust-manifest <file>
+                        also write rust modules naming every sheet of the
+                        output with its animations and tiles
+    --extract-palette <image> <palette>
+                        save the colors of an image as a palette and exit
+    --force             rebuild sheets that are up to date
+    --watch             keep running and repack on changes
+    --dry-run           list what would be generated, write nothing
+    --jobs <n>          threads decoding and packing [default: all cores]
+    -q, --quiet         only print errors
+    -v, --verbose       also print skipped sheets and every frame
+    -h, --help          print this message
+";
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
 
-            for file in WalkDir::new(entry.path())
-                .into_iter()
-                .filter_map(|e| e.ok())
-            {
-                if file.path().extension().map_or(false, |ext| ext == "png") {
-                    let file_stem =
-                        file.path().file_stem().unwrap().to_str().unwrap();
-                    let parts: Vec<&str> = file_stem.split('_').collect();
-                    if parts.len() >= 2 {
-                        let sprite_name = parts[0].to_string();
-                        let img = image::open(file.path())?;
-                        spritesheets
-                            .entry(sprite_name)
-                            .or_default()
-                            .push((file.path().to_path_buf(), img));
-                    }
+struct Args {
+    // relative paths and the defaults resolve against the working directory
+    forge: Forge,
+    // rebuild every sheet even if the manifest says it is up to date
+    force: bool,
+    // keep running and repack whenever the forge directory changes
+    watch: bool,
+    // None uses every core
+    jobs: Option<usize>,
+    verbosity: Verbosity,
+    // reference image and palette file, replaces forging
+    extract_palette: Option<(PathBuf, PathBuf)>,
+}
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            forge: Forge::new("forge", "assets"),
+            force: false,
+            watch: false,
+            jobs: None,
+            verbosity: Verbosity::Normal,
+            extract_palette: None,
+        }
+    }
+}
+impl Args {
+    // None when only the help was asked for
+    fn parse(
+        mut args: impl Iterator<Item = String>,
+    ) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let mut parsed = Self::default();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("{} expects a value", arg))
+            };
+            match arg.as_str() {
+                "--input" => parsed.forge.input = value()?.into(),
+                "--output" => parsed.forge.output = value()?.into(),
+                "--layout" => {
+                    parsed.forge.overrides.layout =
+                        Some(match value()?.as_str() {
+                            "grid" => forge::sheet::SheetLayout::Grid,
+                            "shelf" => forge::sheet::SheetLayout::Shelf,
+                            other => {
+                                return Err(
+                                    format!("unknown layout {}", other).into()
+                                )
+                            }
+                        })
+                }
+                "--max-size" => {
+                    parsed.forge.overrides.max_size = Some(value()?.parse()?)
+                }
+                "--padding" => {
+                    parsed.forge.overrides.padding = Some(value()?.parse()?)
+                }
+                "--extrude" => {
+                    parsed.forge.overrides.extrude = Some(value()?.parse()?)
                 }
+                "--trim" => parsed.forge.overrides.trim = Some(true),
+                "--dedupe-across" => {
+                    parsed.forge.overrides.dedupe_across = Some(true)
+                }
+                "--palette" => {
+                    parsed.forge.overrides.palette = Some(value()?.into())
+                }
+                "--indexed" => parsed.forge.overrides.indexed = Some(true),
+                "--companions" => {
+                    let companions = value()?
+                        .split(',')
+                        .map(|name| match name.trim() {
+                            "outline" => Ok(Companion::Outline),
+                            "silhouette" => Ok(Companion::Silhouette),
+                            "normal" => Ok(Companion::Normal),
+                            other => {
+                                Err(format!("unknown companion {}", other))
+                            }
+                        })
+                        .collect::<Result<_, _>>()?;
+                    parsed.forge.overrides.companions = Some(companions)
+                }
+                "--rust-manifest" => {
+                    parsed.forge.rust_manifest = Some(value()?.into())
+                }
+                "--extract-palette" => {
+                    let image = value()?.into();
+                    parsed.extract_palette = Some((image, value()?.into()))
+                }
+                "--jobs" => parsed.jobs = Some(value()?.parse()?),
+                "--force" => parsed.force = true,
+                "--watch" => parsed.watch = true,
+                "--dry-run" => parsed.forge.dry_run = true,
+                "-q" | "--quiet" => parsed.verbosity = Verbosity::Quiet,
+                "-v" | "--verbose" => parsed.verbosity = Verbosity::Verbose,
+                "-h" | "--help" => return Ok(None),
+                _ if arg.starts_with('-') => {
+                    return Err(
+                        format!("unknown argument {}, see --help", arg).into()
+                    )
+                }
+                _ => parsed.forge.only.push(arg),
             }
+        }
+        Ok(Some(parsed))
+    }
 
-            for (sprite_name, mut images) in spritesheets {
-                images.sort_by_key(|(path, _)| path.clone());
-                let (width, height) = images[0].1.dimensions();
-                let total_width = width * images.len() as u32;
-                let mut spritesheet = ImageBuffer::new(total_width, height);
+    fn says(&self, verbosity: Verbosity) -> bool {
+        self.verbosity >= verbosity
+    }
+}
 
-                for (i, (_, img)) in images.iter().enumerate() {
-                    image::imageops::replace(
-                        &mut spritesheet,
-                        img,
-                        (i as u32 * width) as i64,
-                        0,
-                    );
-                }
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let Some(args) = Args::parse(std::env::args().skip(1))? else {
+        print!("{}", USAGE);
+        return Ok(());
+    };
+
+    if let Some((image_path, palette_path)) = &args.extract_palette {
+        let palette = Palette::extract(&image::open(image_path)?);
+        palette.save(palette_path)?;
+        if args.says(Verbosity::Normal) {
+            println!(
+                "Extracted {} colors into {}",
+                palette.colors.len(),
+                palette_path.display()
+            );
+        }
+        return Ok(());
+    }
 
-                let output_path =
-                    output_dir.join(format!("{}.png", sprite_name));
-                spritesheet.save(&output_path)?;
-                println!("Generated spritesheet: {}", output_path.display());
+    if let Some(jobs) = args.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()?;
+    }
+
+    if !args.watch {
+        if let Err(error) = forge(&args, args.force) {
+            eprintln!("Error: {}", error);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let mut force = args.force;
+    let mut snapshot = watch_snapshot(&args.forge.input);
+    loop {
+        // a broken frame should not stop watching, the next save may fix it
+        if let Err(error) = forge(&args, force) {
+            eprintln!("Error: {}", error);
+        }
+        force = false;
+        if args.says(Verbosity::Normal) {
+            println!("Watching {} for changes...", args.forge.input.display());
+        }
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+            let current = watch_snapshot(&args.forge.input);
+            if current != snapshot {
+                snapshot = current;
+                break;
             }
         }
     }
+}
 
-    println!("Sprite sheet generation completed successfully.");
+fn forge(args: &Args, force: bool) -> Result<(), ForgeError> {
+    let result = args.forge.run(force, |event| match event {
+        Event::Generated { kind, path } => {
+            if args.says(Verbosity::Normal) {
+                println!("Generated {}: {}", kind, path.display());
+            }
+        }
+        Event::Placed { frame, page, x, y } => {
+            if args.says(Verbosity::Verbose) {
+                println!("    {} -> {} at {}, {}", frame, page, x, y);
+            }
+        }
+        Event::UpToDate { meta } => {
+            if args.says(Verbosity::Verbose) {
+                println!("Up to date: {}", meta.display());
+            }
+        }
+        Event::WouldGenerate { meta, frames } => {
+            if args.says(Verbosity::Normal) {
+                println!(
+                    "Would generate: {} ({} frames)",
+                    meta.display(),
+                    frames.len()
+                );
+            }
+            if args.says(Verbosity::Verbose) {
+                for frame in frames {
+                    println!("    {}", frame.display());
+                }
+            }
+        }
+        Event::Removed { path } => {
+            if args.says(Verbosity::Normal) {
+                println!("Removed: {}", path.display());
+            }
+        }
+    });
+    if let Err(ForgeError::Invalid(issues) | ForgeError::OffPalette(issues)) =
+        &result
+    {
+        for issue in issues {
+            eprintln!("{}", issue);
+        }
+    }
+    result?;
+    if !args.forge.dry_run && args.says(Verbosity::Normal) {
+        println!("Sprite sheet generation completed successfully.");
+    }
     Ok(())
 }
+
+// size and modification time of every input file, the manifest excluded
+fn watch_snapshot(forge_dir: &Path) -> BTreeMap<PathBuf, (u64, SystemTime)> {
+    WalkDir::new(forge_dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.file_name() != MANIFEST_FILE)
+        .filter_map(|e| {
+            let metadata = e.metadata().ok()?;
+            let modified = metadata.modified().ok()?;
+            Some((e.into_path(), (metadata.len(), modified)))
+        })
+        .collect()
+}
@@ -0,0 +1,599 @@
+use forge::sheet::{FrameRect, SheetLayout, SheetMeta, TrimMeta};
+use forge::{pack, Companion, Event, Forge, ForgeError, PackOptions};
+use image::{Rgba, RgbaImage};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// an empty directory per test, left behind for inspection on failure
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "forge-{}-{}",
+        std::process::id(),
+        name
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn solid(w: u32, h: u32, color: [u8; 4]) -> RgbaImage {
+    RgbaImage::from_pixel(w, h, Rgba(color))
+}
+
+fn save(image: &RgbaImage, path: &Path) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    image.save(path).unwrap();
+}
+
+fn read_meta(path: &Path) -> SheetMeta {
+    ron::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn rect(x: u32, y: u32, w: u32, h: u32) -> FrameRect {
+    FrameRect { x, y, w, h }
+}
+
+#[test]
+fn grid_places_frames_in_near_square_rows() {
+    let images = (0..5).map(|i| solid(4, 3, [i * 40, 0, 0, 255])).collect();
+    let options = PackOptions {
+        padding: 1,
+        ..Default::default()
+    };
+    let sheet = pack("walk", images, &options).unwrap();
+    assert_eq!((sheet.meta.columns, sheet.meta.rows), (3, 2));
+    assert_eq!(sheet.pages.len(), 1);
+    assert_eq!(sheet.pages[0].dimensions(), (14, 7));
+    let rects: Vec<_> = sheet.meta.frames.iter().map(|f| f.rect).collect();
+    assert_eq!(rects[0], rect(0, 0, 4, 3));
+    assert_eq!(rects[2], rect(10, 0, 4, 3));
+    assert_eq!(rects[4], rect(5, 4, 4, 3));
+    assert_eq!(sheet.pages[0].get_pixel(5, 4), &Rgba([160, 0, 0, 255]));
+    assert_eq!(sheet.meta.animations[0].name, "walk");
+    assert_eq!(sheet.meta.animations[0].last, 4);
+}
+
+#[test]
+fn identical_frames_are_packed_once() {
+    let red = solid(2, 2, [255, 0, 0, 255]);
+    let blue = solid(2, 2, [0, 0, 255, 255]);
+    let images = vec![red.clone(), blue, red];
+    let sheet = pack("blink", images, &PackOptions::default()).unwrap();
+    assert_eq!(sheet.meta.frame_count, 3);
+    assert_eq!(sheet.meta.frames[2].duplicate_of, Some(0));
+    assert_eq!(sheet.meta.frames[2].rect, sheet.meta.frames[0].rect);
+    // two packed frames fit a 2x1 grid
+    assert_eq!(sheet.pages[0].dimensions(), (4, 2));
+}
+
+#[test]
+fn trim_keeps_the_offset_into_the_source_frame() {
+    let mut image = RgbaImage::new(8, 8);
+    for (x, y) in [(2, 3), (4, 5)] {
+        image.put_pixel(x, y, Rgba([0, 255, 0, 255]));
+    }
+    let options = PackOptions {
+        trim: true,
+        ..Default::default()
+    };
+    let sheet = pack("spark", vec![image], &options).unwrap();
+    let frame = &sheet.meta.frames[0];
+    assert_eq!(frame.rect, rect(0, 0, 3, 3));
+    assert_eq!(
+        frame.trim,
+        Some(TrimMeta {
+            x: 2,
+            y: 3,
+            source_w: 8,
+            source_h: 8,
+        })
+    );
+}
+
+#[test]
+fn shelf_splits_into_pages_at_max_size() {
+    let images = (0..6).map(|i| solid(8, 8 - i, [0, 0, 0, 255])).collect();
+    let options = PackOptions {
+        layout: SheetLayout::Shelf,
+        max_size: 16,
+        ..Default::default()
+    };
+    let sheet = pack("rocks", images, &options).unwrap();
+    assert_eq!(sheet.pages.len(), 2);
+    assert_eq!(sheet.meta.pages[1].image, "rocks.1.png");
+    for (frame, page) in sheet.meta.frames.iter().zip([0, 0, 0, 0, 1, 1]) {
+        assert_eq!(frame.page, page);
+    }
+    for (page, meta) in sheet.pages.iter().zip(&sheet.meta.pages) {
+        assert!(page.width() <= 16 && page.height() <= 16);
+        assert_eq!(page.dimensions(), (meta.w, meta.h));
+    }
+}
+
+#[test]
+fn oversized_frames_are_rejected() {
+    let options = PackOptions {
+        max_size: 4,
+        ..Default::default()
+    };
+    let result = pack("huge", vec![solid(5, 2, [0; 4])], &options);
+    assert!(matches!(result, Err(ForgeError::Pack(_))));
+    let result = pack("none", Vec::new(), &options);
+    assert!(matches!(result, Err(ForgeError::Pack(_))));
+}
+
+#[test]
+fn extrude_repeats_the_frame_edges() {
+    let mut image = solid(2, 2, [10, 20, 30, 255]);
+    image.put_pixel(1, 1, Rgba([200, 0, 0, 255]));
+    let options = PackOptions {
+        extrude: 1,
+        ..Default::default()
+    };
+    let sheet = pack("tile", vec![image], &options).unwrap();
+    assert_eq!(sheet.meta.frames[0].rect, rect(1, 1, 2, 2));
+    let page = &sheet.pages[0];
+    assert_eq!(page.dimensions(), (4, 4));
+    assert_eq!(page.get_pixel(0, 0), &Rgba([10, 20, 30, 255]));
+    assert_eq!(page.get_pixel(3, 3), &Rgba([200, 0, 0, 255]));
+}
+
+#[test]
+fn outline_surrounds_the_opaque_pixels() {
+    let mut image = RgbaImage::new(3, 3);
+    image.put_pixel(1, 1, Rgba([9, 9, 9, 255]));
+    let options = PackOptions {
+        companions: vec![Companion::Outline],
+        ..Default::default()
+    };
+    let sheet = pack("dot", vec![image], &options).unwrap();
+    let (companion, pages) = &sheet.companions[0];
+    assert_eq!(*companion, Companion::Outline);
+    assert_eq!(sheet.meta.pages[0].companions["outline"], "dot.outline.png");
+    let outline = &pages[0];
+    assert_eq!(outline.get_pixel(1, 0)[3], 255);
+    assert_eq!(outline.get_pixel(1, 1)[3], 0);
+    assert_eq!(outline.get_pixel(0, 0)[3], 0);
+}
+
+#[test]
+fn run_writes_sheets_and_skips_them_when_unchanged() {
+    let dir = temp_dir("run");
+    let (input, output) = (dir.join("frames"), dir.join("assets"));
+    for i in 0..3 {
+        let frame = solid(4, 4, [i * 80, 0, 0, 255]);
+        save(&frame, &input.join("hero").join(format!("idle_{}.png", i)));
+    }
+    let forge = Forge::new(&input, &output);
+
+    let mut generated = Vec::new();
+    forge
+        .run(false, |event| {
+            if let Event::Generated { kind, path } = event {
+                generated.push((kind, path.to_path_buf()));
+            }
+        })
+        .unwrap();
+    let meta_path = output.join("hero").join("idle.sheet.ron");
+    assert_eq!(
+        generated,
+        [
+            ("spritesheet", output.join("hero").join("idle.png")),
+            ("metadata", meta_path.clone()),
+        ]
+    );
+    let meta = read_meta(&meta_path);
+    assert_eq!(meta.frame_count, 3);
+    assert_eq!(meta.frames[1].source, "idle_1.png");
+
+    let mut up_to_date = 0;
+    forge
+        .run(false, |event| match event {
+            Event::UpToDate { .. } => up_to_date += 1,
+            Event::Generated { .. } => panic!("rebuilt an unchanged sheet"),
+            _ => {}
+        })
+        .unwrap();
+    assert_eq!(up_to_date, 1);
+}
+
+#[test]
+fn outputs_without_inputs_are_removed() {
+    let dir = temp_dir("removed");
+    let (input, output) = (dir.join("frames"), dir.join("assets"));
+    for i in 0..6 {
+        let color = [40 * i as u8, 0, 0, 255];
+        save(&solid(8, 8, color), &input.join(format!("rock_{}.png", i)));
+    }
+    for i in 0..2 {
+        let color = [0, 40 * i as u8, 0, 255];
+        save(&solid(8, 8, color), &input.join(format!("moss_{}.png", i)));
+    }
+    let mut forge = Forge::new(&input, &output);
+    forge.pack.layout = SheetLayout::Shelf;
+    forge.pack.max_size = 16;
+    forge.run(false, |_| {}).unwrap();
+    assert!(output.join("rock.1.png").exists());
+
+    for name in ["rock_4", "rock_5", "moss_0", "moss_1"] {
+        fs::remove_file(input.join(format!("{}.png", name))).unwrap();
+    }
+    // a lost page rebuilds its sheet even with unchanged inputs
+    fs::remove_file(output.join("rock.png")).unwrap();
+    let mut removed = Vec::new();
+    forge
+        .run(false, |event| {
+            if let Event::Removed { path } = event {
+                removed.push(path.to_path_buf());
+            }
+        })
+        .unwrap();
+    removed.sort();
+    assert_eq!(
+        removed,
+        [
+            output.join("moss.png"),
+            output.join("moss.sheet.ron"),
+            output.join("rock.1.png"),
+        ]
+    );
+    assert!(output.join("rock.png").exists());
+}
+
+#[test]
+fn missing_frames_stop_the_run_before_writing() {
+    let dir = temp_dir("missing");
+    let (input, output) = (dir.join("frames"), dir.join("assets"));
+    for i in [0, 1, 3] {
+        save(&solid(2, 2, [0; 4]), &input.join(format!("run_{}.png", i)));
+    }
+    let result = Forge::new(&input, &output).run(false, |_| {});
+    let Err(ForgeError::Invalid(issues)) = result else {
+        panic!("expected invalid input");
+    };
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].path.ends_with("run_3.png"));
+    assert!(issues[0].message.contains("frame 2 is missing"));
+    assert!(!output.exists());
+}
+
+#[test]
+fn lone_frames_are_reported_as_typos() {
+    let dir = temp_dir("lone");
+    let (input, output) = (dir.join("frames"), dir.join("assets"));
+    for i in 1..4 {
+        save(&solid(2, 2, [0; 4]), &input.join(format!("idle_{}.png", i)));
+    }
+    save(&solid(2, 2, [0; 4]), &input.join("idel_4.png"));
+    let result = Forge::new(&input, &output).run(false, |_| {});
+    let Err(ForgeError::Invalid(issues)) = result else {
+        panic!("expected invalid input");
+    };
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].path.ends_with("idel_4.png"));
+    assert!(issues[0].message.contains("only frame of idel"));
+    assert!(!output.exists());
+}
+
+#[test]
+fn facings_become_grid_rows() {
+    let dir = temp_dir("facings");
+    let (input, output) = (dir.join("frames"), dir.join("assets"));
+    for direction in ["s", "n"] {
+        for i in 0..3 {
+            let path = input.join(format!("walk_{}_{}.png", direction, i));
+            save(&solid(2, 2, [0, 0, 0, 255]), &path);
+        }
+    }
+    Forge::new(&input, &output).run(false, |_| {}).unwrap();
+    let meta = read_meta(&output.join("walk.sheet.ron"));
+    assert_eq!((meta.columns, meta.rows), (3, 2));
+    let rows: Vec<_> =
+        meta.directions.iter().map(|d| (&*d.name, d.row)).collect();
+    assert_eq!(rows, [("n", 0), ("s", 1)]);
+    // identical frames keep their place in the row of their facing
+    assert_eq!(meta.frames[4].rect, rect(2, 2, 2, 2));
+    assert_eq!(meta.frames[4].duplicate_of, None);
+    let names: Vec<_> = meta.animations.iter().map(|a| &*a.name).collect();
+    assert_eq!(names, ["walk_n", "walk_s"]);
+}
+
+#[test]
+fn facings_outside_a_grid_are_rejected() {
+    let dir = temp_dir("facings-shelf");
+    let (input, output) = (dir.join("frames"), dir.join("assets"));
+    for direction in ["s", "n"] {
+        for i in 0..2 {
+            let path = input.join(format!("walk_{}_{}.png", direction, i));
+            save(&solid(2, 2, [0, 0, 0, 255]), &path);
+        }
+    }
+    fs::write(input.join("forge.ron"), "(layout: Shelf)").unwrap();
+    let result = Forge::new(&input, &output).run(false, |_| {});
+    let Err(ForgeError::Invalid(issues)) = result else {
+        panic!("expected invalid input");
+    };
+    assert!(issues[0].message.contains("grid layout"));
+    assert!(!output.exists());
+}
+
+#[test]
+fn off_palette_pixels_are_reported_with_coordinates() {
+    let dir = temp_dir("palette");
+    let (input, output) = (dir.join("frames"), dir.join("assets"));
+    let palette = dir.join("palette.hex");
+    fs::write(&palette, "000000\nffffff\n").unwrap();
+    let mut frame = solid(2, 2, [255, 255, 255, 255]);
+    frame.put_pixel(1, 0, Rgba([255, 0, 0, 255]));
+    save(&frame, &input.join("glow_0.png"));
+    save(&solid(2, 2, [0, 0, 0, 255]), &input.join("glow_1.png"));
+    let mut forge = Forge::new(&input, &output);
+    forge.pack.palette = Some(palette);
+    let result = forge.run(false, |_| {});
+    let Err(ForgeError::OffPalette(issues)) = result else {
+        panic!("expected off palette pixels");
+    };
+    assert_eq!(
+        issues[0].message,
+        "1 pixels are off the palette: 1,0 #ff0000"
+    );
+    assert!(!output.exists());
+}
+
+#[test]
+fn tileset_names_tiles_after_their_files() {
+    let dir = temp_dir("tileset");
+    let (input, output) = (dir.join("frames"), dir.join("assets"));
+    let ground = input.join("ground");
+    fs::create_dir_all(&ground).unwrap();
+    fs::write(ground.join("forge.ron"), "(tileset: true)").unwrap();
+    save(&solid(4, 2, [0, 255, 0, 255]), &ground.join("grass.png"));
+    save(&solid(4, 2, [0, 0, 255, 255]), &ground.join("water_0.png"));
+    save(&solid(4, 2, [0, 0, 200, 255]), &ground.join("water_1.png"));
+    Forge::new(&input, &output).run(false, |_| {}).unwrap();
+
+    // written beside the tileset directory, named after it
+    let meta = read_meta(&output.join("ground.sheet.ron"));
+    let tiles: Vec<_> = meta
+        .tiles
+        .iter()
+        .map(|tile| (&*tile.name, tile.first, tile.variants))
+        .collect();
+    assert_eq!(tiles, [("grass", 0, 1), ("water", 1, 2)]);
+    assert!(meta.animations.is_empty());
+    assert!(output.join("ground.png").exists());
+}
+
+#[test]
+fn rust_manifest_names_sheets_animations_and_tiles() {
+    let dir = temp_dir("rust");
+    let (input, output) = (dir.join("frames"), dir.join("assets"));
+    for i in 0..4 {
+        save(
+            &solid(2, 2, [0; 4]),
+            &input.join("hero").join(format!("walk_se_{}.png", i)),
+        );
+    }
+    let ground = input.join("ground");
+    fs::create_dir_all(&ground).unwrap();
+    fs::write(ground.join("forge.ron"), "(tileset: true)").unwrap();
+    save(&solid(2, 2, [0; 4]), &ground.join("deep-water.png"));
+    let mut forge = Forge::new(&input, &output);
+    forge.rust_manifest = Some(dir.join("assets.rs"));
+    forge.run(false, |_| {}).unwrap();
+
+    let source = fs::read_to_string(dir.join("assets.rs")).unwrap();
+    assert_eq!(source, forge::rust_manifest(&output).unwrap());
+    for expected in [
+        "pub mod hero {\n    pub mod walk {",
+        "pub const PATH: &str = \"hero/walk.sheet.ron\";",
+        "Self::WalkSe => \"walk_se\",",
+        "Self::WalkSe => 0..4,",
+        "pub const PATH: &str = \"ground.sheet.ron\";",
+        "Self::DeepWater => 0..1,",
+    ] {
+        assert!(
+            source.contains(expected),
+            "{} missing from\n{}",
+            expected,
+            source
+        );
+    }
+}
+
+#[test]
+fn rust_manifest_escapes_keyword_names() {
+    let dir = temp_dir("keywords");
+    let (input, output) = (dir.join("frames"), dir.join("assets"));
+    for name in ["box", "try", "gen"] {
+        for i in 0..2 {
+            save(
+                &solid(2, 2, [0; 4]),
+                &input.join("items").join(format!("{}_{}.png", name, i)),
+            );
+        }
+    }
+    Forge::new(&input, &output).run(false, |_| {}).unwrap();
+
+    let source = forge::rust_manifest(&output).unwrap();
+    for expected in ["pub mod box_ {", "pub mod try_ {", "pub mod gen_ {"] {
+        assert!(
+            source.contains(expected),
+            "{} missing from\n{}",
+            expected,
+            source
+        );
+    }
+}
+
+#[test]
+fn gaps_near_the_largest_frame_number_are_reported() {
+    let dir = temp_dir("large");
+    let (input, output) = (dir.join("frames"), dir.join("assets"));
+    for i in [u32::MAX - 4, u32::MAX - 1, u32::MAX] {
+        save(&solid(2, 2, [0; 4]), &input.join(format!("far_{}.png", i)));
+    }
+    let result = Forge::new(&input, &output).run(false, |_| {});
+    let Err(ForgeError::Invalid(issues)) = result else {
+        panic!("expected invalid input");
+    };
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].message.contains("4294967292 to 4294967293"));
+}
+
+// header, frame and chunk layouts follow forge::aseprite
+fn aseprite_file(w: u16, h: u16, frames: &[(u16, Vec<Vec<u8>>)]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (duration_ms, chunks) in frames {
+        let count = chunks.len() as u16;
+        let chunks = chunks.concat();
+        body.extend((16 + chunks.len() as u32).to_le_bytes());
+        body.extend(0xF1FAu16.to_le_bytes());
+        body.extend(count.to_le_bytes());
+        body.extend(duration_ms.to_le_bytes());
+        body.extend([0; 2]);
+        body.extend((count as u32).to_le_bytes());
+        body.extend(chunks);
+    }
+    let mut file = Vec::new();
+    file.extend((128 + body.len() as u32).to_le_bytes());
+    file.extend(0xA5E0u16.to_le_bytes());
+    file.extend((frames.len() as u16).to_le_bytes());
+    file.extend(w.to_le_bytes());
+    file.extend(h.to_le_bytes());
+    file.extend(32u16.to_le_bytes());
+    file.extend(1u32.to_le_bytes()); // layer opacity is valid
+    file.resize(128, 0);
+    file.extend(body);
+    file
+}
+
+fn chunk(kind: u16, data: &[u8]) -> Vec<u8> {
+    let mut chunk = (6 + data.len() as u32).to_le_bytes().to_vec();
+    chunk.extend(kind.to_le_bytes());
+    chunk.extend(data);
+    chunk
+}
+
+fn raw_cel(x: i16, y: i16, w: u16, h: u16, color: [u8; 4]) -> Vec<u8> {
+    let mut data = vec![0; 2]; // layer
+    data.extend(x.to_le_bytes());
+    data.extend(y.to_le_bytes());
+    data.push(255);
+    data.extend([0; 2 + 2 + 5]); // raw, z index, reserved
+    data.extend(w.to_le_bytes());
+    data.extend(h.to_le_bytes());
+    data.extend(color.repeat(w as usize * h as usize));
+    chunk(0x2005, &data)
+}
+
+#[test]
+fn aseprite_tags_become_animations_timed_by_their_frames() {
+    let dir = temp_dir("aseprite");
+    let (input, output) = (dir.join("frames"), dir.join("assets"));
+    // one visible layer at full opacity
+    let layer = chunk(0x2004, &[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255]);
+    let mut tags = vec![2, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    for (from, to, name) in [(0u16, 1u16, "idle"), (2, 2, "jump")] {
+        tags.extend(from.to_le_bytes());
+        tags.extend(to.to_le_bytes());
+        tags.extend([0; 13]);
+        tags.extend((name.len() as u16).to_le_bytes());
+        tags.extend(name.as_bytes());
+    }
+    let file = aseprite_file(
+        2,
+        2,
+        &[
+            (
+                100,
+                vec![
+                    layer,
+                    chunk(0x2018, &tags),
+                    raw_cel(0, 0, 2, 2, [255, 0, 0, 255]),
+                ],
+            ),
+            (100, vec![raw_cel(1, 1, 1, 1, [0, 255, 0, 255])]),
+            (200, vec![raw_cel(0, 0, 2, 2, [0, 0, 255, 255])]),
+        ],
+    );
+    fs::create_dir_all(&input).unwrap();
+    fs::write(input.join("hero.aseprite"), file).unwrap();
+    Forge::new(&input, &output).run(false, |_| {}).unwrap();
+
+    let meta = read_meta(&output.join("hero.sheet.ron"));
+    let animations: Vec<_> = meta
+        .animations
+        .iter()
+        .map(|a| (&*a.name, a.first, a.last, a.fps))
+        .collect();
+    assert_eq!(
+        animations,
+        [("idle", 0, 1, Some(10.0)), ("jump", 2, 2, Some(5.0))]
+    );
+    let sheet = image::open(output.join("hero.png")).unwrap().to_rgba8();
+    let pixel = |frame: usize, x: u32, y: u32| {
+        let rect = &meta.frames[frame].rect;
+        sheet.get_pixel(rect.x + x, rect.y + y).0
+    };
+    assert_eq!(pixel(0, 1, 1), [255, 0, 0, 255]);
+    assert_eq!(pixel(1, 0, 0), [0; 4]);
+    assert_eq!(pixel(1, 1, 1), [0, 255, 0, 255]);
+    assert_eq!(pixel(2, 0, 1), [0, 0, 255, 255]);
+}
+
+#[test]
+fn aseprite_palettes_past_the_header_colors_are_rejected() {
+    let mut palette = Vec::new();
+    for value in [1u32, 0, 999_999_999] {
+        palette.extend(value.to_le_bytes());
+    }
+    palette.extend([0; 8]);
+    let file = aseprite_file(1, 1, &[(100, vec![chunk(0x2019, &palette)])]);
+    let Err(error) = forge::aseprite::read(&file) else {
+        panic!("expected a damaged file");
+    };
+    assert!(error.contains("more than 256 colors"));
+}
+
+#[test]
+fn command_line_options_win_over_directory_configs() {
+    let dir = temp_dir("overrides");
+    let (input, output) = (dir.join("frames"), dir.join("assets"));
+    let frames: Vec<_> = (0..2).map(|_| solid(2, 2, [0, 0, 0, 255])).collect();
+    for (i, frame) in frames.iter().enumerate() {
+        save(frame, &input.join(format!("idle_{}.png", i)));
+    }
+    fs::write(input.join("forge.ron"), "(padding: 2, extrude: 1)").unwrap();
+    let mut forge = Forge::new(&input, &output);
+    forge.overrides.padding = Some(0);
+    forge.run(false, |_| {}).unwrap();
+    let meta = read_meta(&output.join("idle.sheet.ron"));
+    let options = PackOptions {
+        extrude: 1,
+        ..Default::default()
+    };
+    let expected = pack("idle", frames, &options).unwrap();
+    assert_eq!(meta.extrude, 1);
+    assert_eq!(meta.frames[1].rect, expected.meta.frames[1].rect);
+}
+
+#[test]
+fn configs_with_a_speed_of_zero_are_rejected() {
+    let dir = temp_dir("fps");
+    let (input, output) = (dir.join("frames"), dir.join("assets"));
+    for i in 0..2 {
+        save(&solid(2, 2, [0; 4]), &input.join(format!("idle_{}.png", i)));
+    }
+    fs::write(
+        input.join("forge.ron"),
+        "(animations: {\"idle\": (fps: 0.0)})",
+    )
+    .unwrap();
+    let result = Forge::new(&input, &output).run(false, |_| {});
+    let Err(ForgeError::Invalid(issues)) = result else {
+        panic!("expected invalid input");
+    };
+    assert!(issues[0].path.ends_with("forge.ron"));
+    assert!(issues[0].message.contains("fps 0"));
+}